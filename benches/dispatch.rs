@@ -0,0 +1,81 @@
+//! Compares filtering the events once per player with routing them in a single pass.
+//!
+//! Requires a nightly compiler, parses a synthetic 10-player log unless a log is given:
+//!
+//! ```text
+//! EVTC_BENCH_LOG=path/to/log.evtc cargo bench --bench dispatch
+//! ```
+#![feature(test)]
+extern crate evtc;
+extern crate test;
+
+use evtc::Event;
+use evtc::EventIteratorExt;
+use evtc::Metadata;
+use evtc::dispatch::Dispatcher;
+use evtc::dispatch::Route;
+use evtc::raw;
+use evtc::raw::CombatEventV1;
+use evtc::statistics::Hits;
+use evtc::statistics::Sink;
+use evtc::synthetic;
+
+use std::env;
+use std::fs::File;
+use std::io::Read;
+
+use test::Bencher;
+
+/// The log in `EVTC_BENCH_LOG`, or a synthetic 10-player log of a 5 minute fight.
+fn read_log() -> Vec<u8> {
+    let path = match env::var("EVTC_BENCH_LOG") {
+        Ok(p)  => p,
+        Err(_) => return synthetic::squad(10, 300000),
+    };
+    let mut buffer = Vec::new();
+
+    File::open(path).expect("Could not open EVTC_BENCH_LOG")
+                    .read_to_end(&mut buffer)
+                    .expect("Could not read EVTC_BENCH_LOG");
+
+    buffer
+}
+
+#[bench]
+fn scan_per_player(b: &mut Bencher) {
+    let buffer = read_log();
+    let evtc = raw::transmute(&buffer);
+    let meta = Metadata::new(&evtc);
+
+    b.iter(|| {
+        meta.agents()
+            .iter()
+            .filter(|a| a.profession().is_player_character())
+            .map(|a| meta.encounter_events().related_to(a).filter_map(Event::into_damage).collect::<Hits>())
+            .collect::<Vec<_>>()
+    });
+}
+
+#[bench]
+fn dispatch_single_pass(b: &mut Bencher) {
+    let buffer = read_log();
+    let evtc = raw::transmute(&buffer);
+    let meta = Metadata::new(&evtc);
+
+    b.iter(|| {
+        let players: Vec<_> = meta.agents().iter().filter(|a| a.profession().is_player_character()).collect();
+        let mut hits: Vec<_> = players.iter().map(|_| Hits::default()).collect();
+
+        {
+            let mut dispatcher: Dispatcher<&CombatEventV1> = Dispatcher::new();
+
+            for (a, h) in players.iter().zip(hits.iter_mut()) {
+                dispatcher.add_agent(a, Route::Related, move |e| if let Some(d) = e.into_damage() { h.add_event(d) });
+            }
+
+            dispatcher.run(meta.encounter_events());
+        }
+
+        hits
+    });
+}
@@ -0,0 +1,95 @@
+//! Compares the array-based intensity stack with the expiry-ordered stack under heavy condition
+//! pressure, eg. bleeding applied by a full squad.
+//!
+//! Also simulates the buffs of all agents of a synthetic 10-player log, with the events routed
+//! to each agent.
+//!
+//! Requires a nightly compiler:
+//!
+//! ```text
+//! cargo bench --bench stack
+//! ```
+#![feature(test)]
+extern crate evtc;
+extern crate test;
+
+use evtc::buff::Expiry;
+use evtc::buff::Intensity;
+use evtc::buff::Replace;
+use evtc::buff::Stack;
+use evtc::buff::table;
+use evtc::dispatch::Dispatcher;
+use evtc::dispatch::Route;
+use evtc::raw;
+use evtc::raw::CombatEventV1;
+use evtc::synthetic;
+use evtc::Event;
+use evtc::Metadata;
+
+use test::Bencher;
+use test::black_box;
+
+/// Applies a stack every few milliseconds and updates between each application, keeping a
+/// few hundred stacks active.
+fn simulate<S: Stack>(stack: &mut S) -> u64 {
+    let mut seed  = 12345u32;
+    let mut total = 0;
+
+    for _ in 0..20000 {
+        seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+
+        total += stack.update((seed >> 8) % 20).stacks;
+
+        stack.push(1000 + (seed >> 4) % 9000, seed % 10);
+    }
+
+    total
+}
+
+#[bench]
+fn intensity_1500(b: &mut Bencher) {
+    b.iter(|| {
+        let mut s = Intensity(Replace, [0; 1500], [0; 1500]);
+
+        black_box(simulate(&mut s))
+    })
+}
+
+#[bench]
+fn expiry_1500(b: &mut Bencher) {
+    b.iter(|| {
+        let mut s = Expiry::<Replace, [u32; 1500]>::default();
+
+        black_box(simulate(&mut s))
+    })
+}
+
+#[bench]
+fn squad_buff_maps(b: &mut Bencher) {
+    let buffer = synthetic::squad(10, 300000);
+    let evtc   = raw::transmute(&buffer);
+    let meta   = Metadata::new(&evtc);
+
+    b.iter(|| {
+        let mut maps: Vec<_> = meta.agents().iter().map(|a| table::Map::new(a.id())).collect();
+
+        {
+            let mut dispatcher: Dispatcher<&CombatEventV1> = Dispatcher::new();
+
+            for (a, m) in meta.agents().iter().zip(maps.iter_mut()) {
+                dispatcher.add_agent(a, Route::Related, move |e| if let Some(b) = e.into_buff() {
+                    m.update(b.time());
+                    m.add_event(b);
+                });
+            }
+
+            dispatcher.run(meta.encounter_events());
+        }
+
+        for m in &mut maps {
+            m.finalize(meta.encounter().end());
+        }
+
+        black_box(maps)
+    })
+}
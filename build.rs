@@ -0,0 +1,119 @@
+//! Generates the `buff_table!` invocation for `evtc::buff::table` from `data/buffs.csv`.
+use std::env;
+use std::fs::File;
+use std::io::Read;
+use std::io::Write;
+use std::path::Path;
+
+const BUFF_DATA: &str = "data/buffs.csv";
+
+const BUFF_TYPES:  &[&str] = &["Boon", "Condition", "Buff", "Debuff", "Item"];
+const CATEGORIES:  &[&str] = &["OffensiveBoon", "DefensiveBoon", "Condition", "Offensive", "Defensive", "Personal", "BossDebuff"];
+const SIDES:       &[&str] = &["Friendly", "Hostile"];
+const STACKS:      &[&str] = &["Duration", "Intensity", "Expiry"];
+const STACK_TYPES: &[&str] = &["Queue", "Replace"];
+
+/// The `stack`, `stack_type` and `max_stacks` combinations with a `Stack` implementation in
+/// `src/buff.rs`, keep in sync with the `impl_*!` invocations there.
+const IMPLEMENTED: &[(&str, &str, usize)] = &[
+    ("Duration",  "Replace", 1),
+    ("Duration",  "Queue",   5),
+    ("Intensity", "Replace", 1),
+    ("Intensity", "Replace", 25),
+    ("Intensity", "Queue",   25),
+    ("Intensity", "Replace", 1500),
+    ("Expiry",    "Replace", 25),
+    ("Expiry",    "Replace", 1500),
+];
+
+/// A single row of the buff data file.
+struct Row<'a> {
+    skill_id:   u16,
+    name:       &'a str,
+    kind:       &'a str,
+    category:   &'a str,
+    side:       &'a str,
+    stack:      &'a str,
+    stack_type: &'a str,
+    max_stacks: usize,
+    profession: Option<&'a str>,
+}
+
+fn one_of<'a>(line: usize, column: &str, value: &'a str, allowed: &[&str]) -> &'a str {
+    if ! allowed.contains(&value) {
+        panic!("{}:{}: invalid {} {:?}, expected one of {:?}", BUFF_DATA, line, column, value, allowed);
+    }
+
+    value
+}
+
+fn parse_row<'a>(line: usize, s: &'a str) -> Row<'a> {
+    let cols: Vec<&str> = s.split(',').map(str::trim).collect();
+
+    if cols.len() != 9 {
+        panic!("{}:{}: expected 9 columns, got {}", BUFF_DATA, line, cols.len());
+    }
+
+    let row = Row {
+        skill_id:   cols[0].parse().unwrap_or_else(|_| panic!("{}:{}: invalid skill_id {:?}", BUFF_DATA, line, cols[0])),
+        name:       cols[1],
+        kind:       one_of(line, "type", cols[2], BUFF_TYPES),
+        category:   one_of(line, "category", cols[3], CATEGORIES),
+        side:       one_of(line, "side", cols[4], SIDES),
+        stack:      one_of(line, "stack", cols[5], STACKS),
+        stack_type: one_of(line, "stack_type", cols[6], STACK_TYPES),
+        max_stacks: cols[7].parse().unwrap_or_else(|_| panic!("{}:{}: invalid max_stacks {:?}", BUFF_DATA, line, cols[7])),
+        profession: if cols[8].is_empty() { None } else { Some(cols[8]) },
+    };
+
+    if ! IMPLEMENTED.contains(&(row.stack, row.stack_type, row.max_stacks)) {
+        panic!("{}:{}: {}<{}, [u32; {}]> is not implemented, expected one of {:?}", BUFF_DATA, line, row.stack, row.stack_type, row.max_stacks, IMPLEMENTED);
+    }
+
+    row
+}
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    println!("cargo:rerun-if-changed={}", BUFF_DATA);
+
+    let mut data = String::new();
+
+    File::open(BUFF_DATA).and_then(|mut f| f.read_to_string(&mut data)).expect("Failed to read buff data");
+
+    let rows: Vec<Row> = data.lines()
+                             .enumerate()
+                             .map(|(i, l)| (i + 1, l.trim()))
+                             .filter(|&(_, l)| ! l.is_empty() && ! l.starts_with('#'))
+                             // Header
+                             .skip(1)
+                             .map(|(i, l)| parse_row(i, l))
+                             .collect();
+
+    let mut out = String::from("buff_table!(\npub table {\n");
+
+    for r in &rows {
+        if rows.iter().filter(|o| o.skill_id == r.skill_id).count() > 1 {
+            panic!("{}: duplicate skill_id {}", BUFF_DATA, r.skill_id);
+        }
+
+        out.push_str(&format!(
+            "    BuffType::{}{{{:?}, skill_id: {}, stack: {}<{}, [u32; {}]>, category: BuffCategory::{}, side: BuffSide::{}, profession: {}}},\n",
+            r.kind,
+            r.name,
+            r.skill_id,
+            r.stack,
+            r.stack_type,
+            r.max_stacks,
+            r.category,
+            r.side,
+            r.profession.map(|p| format!("Some(Profession::{})", p)).unwrap_or_else(|| "None".to_owned()),
+        ));
+    }
+
+    out.push_str("});\n");
+
+    let dest = Path::new(&env::var("OUT_DIR").unwrap()).join("buff_table.rs");
+
+    File::create(dest).and_then(|mut f| f.write_all(out.as_bytes())).expect("Failed to write buff table");
+}
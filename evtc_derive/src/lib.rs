@@ -0,0 +1,308 @@
+//! Derive-macros for the `evtc` crate.
+//!
+//! ## `#[derive(Sink)]`
+//!
+//! Implements `evtc::statistics::Sink<T>` and `FromIterator<T>` for a struct by forwarding each
+//! event to every field. Each field has to implement `Sink<T>` itself.
+//!
+//! ```ignore
+//! #[derive(Default, Sink)]
+//! #[sink(event = "Damage")]
+//! struct PowerCondiHits {
+//!     #[sink(exclude_hit_type = "Condi")]
+//!     power: Hits,
+//!     #[sink(hit_type = "Condi")]
+//!     condi: Hits,
+//! }
+//! ```
+//!
+//! Struct attributes:
+//!
+//!  * `event = "Trait"`: Trait bound for the event type, defaults to `evtc::event::Event`.
+//!
+//! Field attributes:
+//!
+//!  * `skip`: Do not forward any events to this field.
+//!  * `hit_type = "Crit | Normal"`: Only forward damage events with any of the listed hit types.
+//!  * `exclude_hit_type = "Condi"`: Only forward damage events without any of the listed hit types.
+//!  * `filter = "Predicate"`: Only forward events matching the `evtc::statistics::Predicate`.
+//!  * `target_in = "field"`: Only forward events where the target agent is contained in the given
+//!    field (eg. a `Vec<AgentId>` marked with `skip`).
+//!
+//! ## `#[derive(Merge)]`
+//!
+//! Implements `evtc::statistics::Merge` for a struct by merging each field. Fields marked with
+//! `#[merge(skip)]` are left untouched.
+extern crate proc_macro;
+extern crate proc_macro2;
+#[macro_use]
+extern crate quote;
+#[macro_use]
+extern crate syn;
+
+use proc_macro::TokenStream;
+
+use proc_macro2::Span;
+use proc_macro2::TokenStream as TokenStream2;
+
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Field;
+use syn::Fields;
+use syn::Ident;
+use syn::Lit;
+use syn::Meta;
+use syn::NestedMeta;
+use syn::Path;
+use syn::punctuated::Punctuated;
+
+#[proc_macro_derive(Sink, attributes(sink))]
+pub fn derive_sink(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match impl_sink(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err)   => err.to_compile_error().into(),
+    }
+}
+
+#[proc_macro_derive(Merge, attributes(merge))]
+pub fn derive_merge(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+
+    match impl_merge(&input) {
+        Ok(tokens) => tokens.into(),
+        Err(err)   => err.to_compile_error().into(),
+    }
+}
+
+/// Options for a single field.
+#[derive(Default)]
+struct FieldOptions {
+    skip:             bool,
+    hit_types:        Vec<Ident>,
+    exclude_hit_type: Vec<Ident>,
+    filters:          Vec<Path>,
+    target_in:        Option<Ident>,
+}
+
+/// Returns the `#[sink(...)]` name-value pairs and words of the attributes.
+fn sink_attributes(attrs: &[syn::Attribute]) -> Result<Vec<Meta>, Error> {
+    attributes(attrs, "sink")
+}
+
+/// Returns the `#[name(...)]` name-value pairs and words of the attributes.
+fn attributes(attrs: &[syn::Attribute], name: &str) -> Result<Vec<Meta>, Error> {
+    let mut metas = Vec::new();
+
+    for attr in attrs.iter().filter(|a| a.path.is_ident(name)) {
+        match attr.parse_meta()? {
+            Meta::List(list) => for nested in list.nested {
+                match nested {
+                    NestedMeta::Meta(m) => metas.push(m),
+                    other               => return Err(Error::new_spanned(other, "expected a sink option")),
+                }
+            },
+            other => return Err(Error::new_spanned(other, "expected a list of options")),
+        }
+    }
+
+    Ok(metas)
+}
+
+fn string_value(meta: &Meta) -> Result<syn::LitStr, Error> {
+    match meta {
+        Meta::NameValue(syn::MetaNameValue { lit: Lit::Str(s), .. }) => Ok(s.clone()),
+        other => Err(Error::new_spanned(other, "expected a string value")),
+    }
+}
+
+/// Parses a list of hit-types separated by `|`.
+fn hit_types(meta: &Meta) -> Result<Vec<Ident>, Error> {
+    let s = string_value(meta)?;
+
+    s.value().split('|').map(|t| syn::parse_str::<Ident>(t.trim()).map_err(|_| Error::new_spanned(&s, "expected a HitType variant"))).collect()
+}
+
+fn field_options(attrs: &[syn::Attribute]) -> Result<FieldOptions, Error> {
+    let mut opts = FieldOptions::default();
+
+    for meta in sink_attributes(attrs)? {
+        match meta.name().to_string().as_str() {
+            "skip"             => opts.skip = true,
+            "hit_type"         => opts.hit_types.extend(hit_types(&meta)?),
+            "exclude_hit_type" => opts.exclude_hit_type.extend(hit_types(&meta)?),
+            "filter"           => opts.filters.push(string_value(&meta)?.parse()?),
+            "target_in"        => opts.target_in = Some(string_value(&meta)?.parse()?),
+            _                  => return Err(Error::new_spanned(meta, "unknown sink field option")),
+        }
+    }
+
+    Ok(opts)
+}
+
+fn named_fields<'a>(input: &'a DeriveInput, derive: &str) -> Result<&'a Punctuated<Field, Token![,]>, Error> {
+    match input.data {
+        Data::Struct(ref s) => match s.fields {
+            Fields::Named(ref f) => Ok(&f.named),
+            _                    => Err(Error::new_spanned(input, format!("{} can only be derived for structs with named fields", derive))),
+        },
+        _ => Err(Error::new_spanned(input, format!("{} can only be derived for structs", derive))),
+    }
+}
+
+fn impl_sink(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name   = &input.ident;
+    let fields = named_fields(input, "Sink")?;
+
+    let mut event_bound: Path = syn::parse_quote!(::evtc::event::Event);
+
+    for meta in sink_attributes(&input.attrs)? {
+        match meta.name().to_string().as_str() {
+            "event" => event_bound = string_value(&meta)?.parse()?,
+            _       => return Err(Error::new_spanned(meta, "unknown sink option")),
+        }
+    }
+
+    let event = Ident::new("__E", Span::call_site());
+    let mut bounds     = vec![quote!(#event: #event_bound + Clone)];
+    let mut forwards   = Vec::new();
+
+    for field in fields.iter() {
+        let opts  = field_options(&field.attrs)?;
+        let ident = field.ident.as_ref().unwrap();
+        let ty    = &field.ty;
+
+        if opts.skip {
+            continue;
+        }
+
+        let mut conditions = Vec::new();
+
+        if ! opts.hit_types.is_empty() || ! opts.exclude_hit_type.is_empty() {
+            bounds.push(quote!(#event: ::evtc::event::Damage));
+        }
+
+        if ! opts.hit_types.is_empty() {
+            let types = &opts.hit_types;
+
+            conditions.push(quote!(match ::evtc::event::Damage::hit_type(&e) { #(::evtc::event::HitType::#types)|* => true, _ => false }));
+        }
+
+        if ! opts.exclude_hit_type.is_empty() {
+            let types = &opts.exclude_hit_type;
+
+            conditions.push(quote!(match ::evtc::event::Damage::hit_type(&e) { #(::evtc::event::HitType::#types)|* => false, _ => true }));
+        }
+
+        for filter in &opts.filters {
+            bounds.push(quote!(#filter: ::evtc::statistics::Predicate<#event>));
+            conditions.push(quote!(<#filter as ::evtc::statistics::Predicate<#event>>::test(&e)));
+        }
+
+        if let Some(ref target) = opts.target_in {
+            bounds.push(quote!(#event: ::evtc::event::Target));
+            conditions.push(quote!(self.#target.contains(&::evtc::event::Target::target_agent(&e))));
+        }
+
+        bounds.push(quote!(#ty: ::evtc::statistics::Sink<#event>));
+        forwards.push((ident, conditions));
+    }
+
+    // The last field receives the event itself instead of a clone
+    let last       = forwards.len().saturating_sub(1);
+    let statements = forwards.iter().enumerate().map(|(i, &(ident, ref conditions))| {
+        let e   = if i == last { quote!(e) } else { quote!(e.clone()) };
+        let add = quote!(::evtc::statistics::Sink::add_event(&mut self.#ident, #e););
+
+        if conditions.is_empty() {
+            add
+        }
+        else {
+            quote!(if #(#conditions)&&* { #add })
+        }
+    });
+
+    let mut generics = input.generics.clone();
+
+    generics.params.push(syn::parse_quote!(#event));
+
+    let (impl_generics, _, _)          = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let where_preds = where_clause.map(|w| {
+        let p = w.predicates.iter();
+
+        quote!(#(#p,)*)
+    });
+
+    let bounds = &bounds;
+
+    Ok(quote! {
+        impl #impl_generics ::evtc::statistics::Sink<#event> for #name #ty_generics
+          where #where_preds #(#bounds),* {
+            #[inline]
+            #[allow(unused_variables)]
+            fn add_event(&mut self, e: #event) {
+                #(#statements)*
+            }
+        }
+
+        impl #impl_generics ::std::iter::FromIterator<#event> for #name #ty_generics
+          where #where_preds #(#bounds),* {
+            fn from_iter<I: IntoIterator<Item=#event>>(iter: I) -> Self {
+                let mut s: Self = Default::default();
+
+                for e in iter {
+                    ::evtc::statistics::Sink::add_event(&mut s, e);
+                }
+
+                s
+            }
+        }
+    })
+}
+
+fn impl_merge(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let name       = &input.ident;
+    let fields     = named_fields(input, "Merge")?;
+    let mut bounds = Vec::new();
+    let mut merges = Vec::new();
+
+    for field in fields.iter() {
+        let ident = field.ident.as_ref().unwrap();
+        let ty    = &field.ty;
+        let mut skip = false;
+
+        for meta in attributes(&field.attrs, "merge")? {
+            match meta.name().to_string().as_str() {
+                "skip" => skip = true,
+                _      => return Err(Error::new_spanned(meta, "unknown merge field option")),
+            }
+        }
+
+        if skip {
+            continue;
+        }
+
+        bounds.push(quote!(#ty: ::evtc::statistics::Merge));
+        merges.push(quote!(::evtc::statistics::Merge::merge(&mut self.#ident, &other.#ident);));
+    }
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let where_preds = where_clause.map(|w| {
+        let p = w.predicates.iter();
+
+        quote!(#(#p,)*)
+    });
+
+    Ok(quote! {
+        impl #impl_generics ::evtc::statistics::Merge for #name #ty_generics
+          where #where_preds #(#bounds),* {
+            #[allow(unused_variables)]
+            fn merge(&mut self, other: &Self) {
+                #(#merges)*
+            }
+        }
+    })
+}
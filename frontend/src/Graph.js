@@ -32,6 +32,14 @@ export const fulltimeAvg   = (series: [number, number], start) => {
   return r;
 };
 export const downed       = ({ downed }) => downed;
+// Values of a buff per bucket of a `buffSeries`, the uptime or the average stacks relative to
+// `maxStacks` if set
+export const buffSeries   = ({ start, bucket, buffs }, skillId, maxStacks) => {
+  const { uptime=[], averageStacks=[] } = buffs[skillId] || {};
+  const values = maxStacks ? averageStacks.map(s => s / maxStacks) : uptime;
+
+  return values.map((v, i) => [(start + i * bucket) / 1000, v]);
+};
 
 // TODO: Generalize these
 export const HealthGraph = ({ series, start: _s, end: _e, x, xScale: _xS, yScale, ...rest }) => {
@@ -48,11 +56,12 @@ export const DPSGraph = ({ series, x, start, end, xScale, yScale, ...rest }) =>
   return <path d={lineGraph(series)} {...rest} />
 }
 
-export const GroupedGraphs = ({ mouseX, children, padding, width, height, start, end, x: _, xScale, yScale, ...rest }) => {
+// Graphs sharing the y-axis, scaled to the largest value unless a `domain` is given
+export const GroupedGraphs = ({ mouseX, children, padding, width, height, start, end, domain, x: _, xScale, yScale, ...rest }) => {
   const allSeries = children.map(c => c.attributes && c.attributes.series ? c.attributes.series : []);
   const x         = n => xScale(fst(n));
 
-  yScale.domain([max(allSeries.map(s => max(s, snd))), 0]);
+  yScale.domain(domain || [max(allSeries.map(s => max(s, snd))), 0]);
 
   return <g>
     {children.map(c => cloneElement(c, { start, padding, width, height, start, end, x, xScale, yScale: yScale.copy() }))}
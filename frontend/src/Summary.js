@@ -15,6 +15,7 @@ import { downedIcon
        } from "./icons";
 import { damageSeries
        , bossDmgSeries
+       , buffSeries
        , fulltimeAvg
        , Axis
        , TimeAxis
@@ -238,28 +239,52 @@ class PlayerBoonRow extends Component {
   }
 }
 
-const buffSort = ({ name: a }, { name: b }) => a.localeCompare(b);
+// Column order of the buff categories in the boon table, from the `buffs` section of the JSON
+const BUFF_CATEGORIES = ["OffensiveBoon", "DefensiveBoon", "Offensive", "Defensive", "Personal"];
 
-@contextData(({ location: { search } }, { players, buffs }) => ({ buffs, players, sort: sortFromParams(parseQueryString(search)) }))
+const buffSort = ({ name: a, category: ca }, { name: b, category: cb }) =>
+  BUFF_CATEGORIES.indexOf(ca) - BUFF_CATEGORIES.indexOf(cb) || a.localeCompare(b);
+
+@contextData(({ location: { search } }, { encounter, players, buffs }) => {
+  const params = parseQueryString(search);
+
+  return { buffs, encounter, players, boon: params.sort === "boon" ? params.boon : null, sort: sortFromParams(params) };
+})
 export class BoonTable extends Component {
-  render({ sort, buffs, players }) {
+  render({ sort, boon, buffs, encounter, players }) {
     const sorted  = players.slice().sort(sort);
     const grouped = groupBy(sorted, ({ agent: { subgroup }}) => subgroup);
 
-    const buffsSorted = Object.values(buffs).sort(buffSort);
+    const buffsSorted = Object.values(buffs).filter(({ side }) => side === "Friendly").sort(buffSort);
     const BuffHeading = ({ name, skillId }) => <TH sort="boon" boon={skillId} title={name}>{
       buffIcons[name] ? <img alt={name} src={buffIcons[name]} /> : name
     }</TH>;
 
-    return <table>
-      <tr>
-        <th></th>
-        <TH sort="name" title="Character name">Name</TH>
-        <TH sort="group" title="Subgroup">Group</TH>
-        {buffsSorted.map(BuffHeading)}
-      </tr>
-      {sorted.map(p => <PlayerBoonRow {...p} buffOrder={buffsSorted} />)}
-    </table>;
+    // Uptime over time of the boon the table is sorted by
+    const meta      = boon ? buffs[boon] : null;
+    const maxStacks = meta && meta.stack.type === "Intensity" ? meta.stack.max : null;
+
+    return <div>
+      {meta ? <ResponsiveGraph class="graph">
+        <Graph start={encounter.seriesStart / 1000} end={encounter.seriesEnd / 1000} width="1500" height="300">
+          <GroupedGraphs domain={[1, 0]}>
+            {sorted.map(p => <DPSGraph class="line" style={{ stroke: professionColour(p.agent) }} series={buffSeries(p.buffSeries, boon, maxStacks)} />)}
+            <Axis format={v => `${Math.round(v * 100)}%`} class="uptime-axis" />
+          </GroupedGraphs>
+          <TimeAxis class="time-axis" />
+        </Graph>
+      </ResponsiveGraph> : null}
+
+      <table>
+        <tr>
+          <th></th>
+          <TH sort="name" title="Character name">Name</TH>
+          <TH sort="group" title="Subgroup">Group</TH>
+          {buffsSorted.map(BuffHeading)}
+        </tr>
+        {sorted.map(p => <PlayerBoonRow {...p} buffOrder={buffsSorted} />)}
+      </table>
+    </div>;
   }
 }
 
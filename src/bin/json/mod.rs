@@ -4,70 +4,100 @@ use evtc::Agent;
 use evtc::AgentId;
 use evtc::Boss;
 use evtc::Damage;
+use evtc::Encounter;
+use evtc::EncounterEnd;
+use evtc::EncounterStart;
 use evtc::Event;
-use evtc::EventIteratorExt;
-use evtc::HitType;
 use evtc::Language;
 use evtc::Metadata;
 use evtc::SkillList;
 use evtc::SpeciesId;
+use evtc::TargetSet;
 use evtc::TimeSeries;
+use evtc::dispatch;
+use evtc::dispatch::Dispatcher;
+use evtc::dispatch::Route;
+use evtc::phase;
+use evtc::phase::Invulnerability;
 use evtc::timeseries::Series;
 use evtc::timeseries::Entry;
-use evtc::buff::MetadataMap;
-use evtc::buff::BuffSnapshot;
+use evtc::buff::StackMeta;
+use evtc::buff::BuffSeries;
+use evtc::buff::BuffUptime;
+use evtc::buff::initial_buffs;
+use evtc::buff::Generation;
 use evtc::buff::table as buffs;
+use evtc::event::EventKind;
 use evtc::event::Source;
 use evtc::event::StateChange;
 use evtc::event::raw::CombatEventV1;
 use evtc::raw;
 use evtc::statistics::Abilities;
 use evtc::statistics::ActivationLog;
+use evtc::statistics::ActiveTime;
+use evtc::statistics::Dispels;
 use evtc::statistics::Hits;
+use evtc::statistics::Merge;
+use evtc::statistics::PerSource;
+use evtc::statistics::PerTarget;
+use evtc::statistics::Phased;
+use evtc::statistics::Rates;
 use evtc::statistics::Sink;
+use evtc::statistics::merged;
+use evtc::time::Timestamp;
+
+use serde::ser::Serialize;
+use serde::ser::Serializer;
+use serde::ser::SerializeMap;
 
 use serde_json;
 
+use std::collections::BTreeMap;
 use std::io::Write;
+use std::iter;
 
 use serde_json::error::Error as JSONError;
 
 /// Separated hit-statistics depending on damage-type
-#[derive(Debug, Clone, Default, Serialize)]
+#[derive(Debug, Clone, Default, Serialize, Sink, Merge)]
+#[sink(event = "Damage")]
 pub struct PowerCondiHits {
+    #[sink(exclude_hit_type = "Condi")]
     power: Hits,
+    #[sink(hit_type = "Condi")]
     condi: Hits,
 }
 
-// TODO: Should probably implement using derive
-impl<T: Damage> Sink<T> for PowerCondiHits {
-    #[inline]
-    fn add_event(&mut self, e: T) {
-        match e.hit_type() {
-            HitType::Condi => self.condi.add_event(e),
-            _              => self.power.add_event(e),
+impl PowerCondiHits {
+    /// Derived metrics, durations in milliseconds.
+    fn rates(&self, duration: u64, active: u64) -> PowerCondiRates {
+        let mut total = self.power;
+
+        total.merge(&self.condi);
+
+        PowerCondiRates {
+            total: total.rates(duration, active),
+            power: self.power.rates(duration, active),
+            condi: self.condi.rates(duration, active),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Default)]
+/// Derived metrics for [PowerCondiHits]
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PowerCondiRates {
+    total: Rates,
+    power: Rates,
+    condi: Rates,
+}
+
+#[derive(Debug, Clone, Serialize, Default, Sink, Merge)]
+#[sink(event = "Damage")]
 pub struct AbilityAndTotal {
     total:     Hits,
     abilities: Abilities,
 }
 
-impl<T: Damage> Sink<T> for AbilityAndTotal {
-    #[inline]
-    fn add_event(&mut self, e: T) {
-        self.total.add_event(e.clone());
-        self.abilities.add_event(e);
-    }
-}
-
-// Should probably be a part of the derive
-sink_from_iter!(PowerCondiHits, Damage);
-sink_from_iter!(AbilityAndTotal, Damage);
-
 #[derive(Debug, Clone, Serialize)]
 struct AgentStatistics<'a> {
     agent:     &'a Agent,
@@ -87,10 +117,25 @@ impl<'a> AgentStatistics<'a> {
     }
 }
 
+/// Damage done to a single target agent.
+#[derive(Debug, Clone, Serialize)]
+struct TargetStatistics<'a> {
+    agent: &'a Agent,
+    hits:  PowerCondiHits,
+}
+
+impl<'a> TargetStatistics<'a> {
+    fn new(agent: &'a Agent) -> Self {
+        TargetStatistics {
+            agent,
+            hits: Default::default(),
+        }
+    }
+}
+
 #[derive(Default, Serialize)]
 struct TimeEntry {
     time:        u64,
-    buffs:       Option<FnvHashMap<u16, BuffSnapshot>>,
     health:      Option<u64>,
     damage:      i64,
     #[serde(rename="bossDmg")]
@@ -123,16 +168,58 @@ struct PlayerSummary<'a, E: Event> {
     #[serde(rename="bossHits")]
     boss_hit_stats:     PowerCondiHits,
     agents:             Vec<AgentStatistics<'a>>,
+    #[serde(rename="targetHits")]
+    target_hits:        Vec<TargetStatistics<'a>>,
+    /// Damage done to every enemy, NPCs grouped by species
+    #[serde(rename="enemyHits")]
+    enemy_hits:         PerTarget<PowerCondiHits>,
     #[serde(rename="activationLog")]
     activation_log:     ActivationLog,
     buffs:              buffs::Map<E::BuffEvent>,
+    /// Per-second buff uptime and average stacks
+    #[serde(rename="buffSeries")]
+    buff_series:        BuffSeries,
+    /// Buffs generated by the player and its minions/gadgets on the squad
+    generation:         Generation,
+    /// Boon strips and condition cleanses
+    dispels:            Dispels,
     #[serde(rename="incomingDamage")]
     incoming_damage:    AbilityAndTotal,
+    /// Incoming damage per skill, NPCs grouped by species
+    #[serde(rename="incomingDamageBySource")]
+    incoming_skills:    PerSource<Abilities>,
     series:             Series<TimeEntry>,
+    /// Time spent alive and connected during the encounter
+    #[serde(rename="activeTime")]
+    active_time:        ActiveTime,
+    #[serde(rename="hitRates")]
+    hit_rates:          PowerCondiRates,
+    #[serde(rename="bossHitRates")]
+    boss_hit_rates:     PowerCondiRates,
+    /// Damage to the targets per phase, see [phase::phases]
+    #[serde(rename="bossPhaseHits")]
+    boss_phase_hits:    Phased<PowerCondiHits>,
+    #[serde(rename="bossPhaseRates")]
+    phase_rates:        Vec<PowerCondiRates>,
+    /// Metrics over the time the bosses were vulnerable
+    #[serde(rename="effectiveBossHitRates")]
+    effective_rates:    PowerCondiRates,
+    /// Time of death, server unix timestamp in milliseconds
+    #[serde(rename="diedAtUtc")]
+    died_at_utc:        Option<u64>,
+    /// The agents which count as boss damage
+    #[serde(skip)]
+    target_ids:         Vec<AgentId>,
+    /// Time of the last event, milliseconds
+    #[serde(skip)]
+    time:               u64,
+    /// Length of the encounter, milliseconds
+    #[serde(skip)]
+    duration:           u64,
 }
 
 impl<'a, E: Source> PlayerSummary<'a, E> {
-    fn new(meta: &'a Metadata<'a>, agent: &'a Agent) -> Self {
+    fn new(meta: &'a Metadata<'a>, agent: &'a Agent, targets: &[&'a Agent], phases: &[Encounter]) -> Self {
         let gadgets = group_agents_by_species(meta.agents_for_master(agent));
 
         PlayerSummary {
@@ -143,35 +230,53 @@ impl<'a, E: Source> PlayerSummary<'a, E> {
                                              .chain(gadgets.values())
                                              .map(AgentStatistics::new)
                                              .collect(),
+            target_hits:     targets.iter().cloned().map(TargetStatistics::new).collect(),
+            enemy_hits:      PerTarget::new(meta),
             activation_log:  Default::default(),
             incoming_damage: Default::default(),
+            incoming_skills: PerSource::new(meta),
             buffs:           buffs::Map::new(agent.id()),
+            buff_series:     BuffSeries::new(meta.encounter().start(), 1000),
+            generation:      Default::default(),
+            dispels:         Dispels::new(meta, agent),
             series:          Series::new(meta),
+            active_time:     ActiveTime::new(agent, meta.encounter()),
+            hit_rates:       Default::default(),
+            boss_hit_rates:  Default::default(),
+            boss_phase_hits: Phased::new(phases),
+            phase_rates:     Vec::new(),
+            effective_rates: Default::default(),
+            died_at_utc:     died_at_utc(meta, agent),
+            target_ids:      targets.iter().map(|a| a.id()).collect(),
+            time:            0,
+            duration:        meta.encounter().duration_ms(),
         }
     }
 
-    // TODO: Do we really filter events before this?
-    fn parse<I: Iterator<Item=E>>(mut self, bosses: &[AgentId], i: I) -> Self {
-        let mut t = 0;
-
-        for event in i {
+    /// Adds an event related to the player, events have to be added in time-order.
+    fn add_event(&mut self, event: E) {
+        {
             // We only store entries per second
             let entry = self.series.current(event.time() / 1000);
 
-            if event.time() != t {
-                self.buffs.update(event.time());
+            if event.time() != self.time {
+                self.buff_series.update(&mut self.buffs, event.time());
 
-                t = event.time();
+                self.time = event.time();
             }
 
-            // Snapshot buffs
-            if entry.buffs.is_none() {
-                entry.buffs = Some(self.buffs.snapshots().collect());
+            self.active_time.add_event(event.clone());
+
+            // Done by the player or its gadgets/minions
+            let own = event.clone().from_agent_or_gadgets(self.agent.id(), self.agent.instance_id());
+
+            if let Some(e) = own.clone() {
+                self.activation_log.add_event(e);
             }
 
             // Parse
-            if let Some(state) = event.state_change() {
-                match state {
+            match event.classify() {
+                Some(EventKind::StateChange { change, .. }) => match change {
                     StateChange::ChangeDown      => entry.downed      = true,
                     StateChange::ChangeUp        => entry.revived     = true,
                     // Got to check if it is a minion which died
@@ -179,83 +284,150 @@ impl<'a, E: Source> PlayerSummary<'a, E> {
                     StateChange::HealthUpdate(h) => entry.health      = Some(h),
                     StateChange::WeaponSwap      => entry.weapon_swap = true,
                     _ => {},
-                }
-            }
-
-            if let Some(b) = event.clone().into_buff() {
-                self.buffs.add_event(b);
-            }
-
-            if let Some(e) = event.clone()
-                                  .targeting_agent(self.agent.id())
-                                  .and_then(Event::into_damage) {
-                self.incoming_damage.add_event(e);
-            }
+                },
+                Some(EventKind::BuffApply { .. }) | Some(EventKind::BuffRemove { .. }) => if let Some(b) = event.into_buff() {
+                    self.buffs.add_event(b.clone());
+                    self.dispels.add_event(b);
+                },
+                Some(EventKind::Damage { agent, target, damage, .. }) => if let Some(d) = event.into_damage() {
+                    if target == self.agent.id() {
+                        self.incoming_damage.add_event(d.clone());
+                        self.incoming_skills.add_event(d.clone());
+                    }
 
-            if let Some(e) = event.clone()
-                              .from_agent_or_gadgets(self.agent.id(), self.agent.instance_id()) {
-                self.activation_log.add_event(e.clone());
+                    if own.is_none() {
+                        return;
+                    }
 
-                if let Some(d) = e.clone()
-                                  .into_damage() {
                     self.hit_stats.add_event(d.clone());
+                    self.enemy_hits.add_event(d.clone());
 
-                    entry.damage += d.damage();
+                    entry.damage += damage;
 
-                    if let Some(b) = d.clone()
-                                      .targeting_any_of(bosses.iter().cloned()) {
-                        self.boss_hit_stats.add_event(b.clone());
+                    if self.target_ids.contains(&target) {
+                        self.boss_hit_stats.add_event(d.clone());
+                        self.boss_phase_hits.add_event(d.clone());
 
-                        entry.boss_dmg += b.damage();
+                        entry.boss_dmg += damage;
 
                         for a in &mut self.agents {
-                            if a.agent_ids.contains(&e.agent()) {
-                                a.stats.add_event(b.clone());
+                            if a.agent_ids.contains(&agent) {
+                                a.stats.add_event(d.clone());
+                            }
+                        }
+
+                        for t in &mut self.target_hits {
+                            if t.agent.id() == target {
+                                t.hits.add_event(d.clone());
                             }
                         }
                     }
-                }
+                },
+                _ => {},
             }
         }
-
-        self
     }
 
-    fn finalize(mut self, time: u64) -> Self {
-        {
-            let entry = self.series.current(time / 1000);
+    fn finalize(&mut self, time: u64) {
+        self.series.current(time / 1000);
+        self.buff_series.finalize(&mut self.buffs, time);
 
-            self.buffs.update(time);
-            self.buffs.finalize(time);
+        self.series.finalize();
 
-            // Snapshot buffs
-            if entry.buffs.is_none() {
-                entry.buffs = Some(self.buffs.snapshots().collect());
-            }
-        }
+        self.hit_rates      = self.hit_stats.rates(self.duration, self.active_time.active());
+        self.boss_hit_rates = self.boss_hit_stats.rates(self.duration, self.active_time.active());
 
-        self.series.finalize();
+        // The phases are the windows where the bosses are vulnerable
+        let phases     = self.boss_phase_hits.phases();
+        let vulnerable = phases.iter().map(|p| p.0.duration_ms()).sum();
+        let active     = phases.iter().map(|p| self.active_time.active_within(p.0.start(), p.0.end())).sum();
 
-        self
+        self.effective_rates = self.boss_hit_stats.rates(vulnerable, active);
+        self.phase_rates     = phases.iter().map(|(p, h)| h.rates(p.duration_ms(), self.active_time.active_within(p.start(), p.end()))).collect();
+    }
+}
+
+/// Statistics aggregated over a group of players.
+#[derive(Debug, Clone, Serialize)]
+struct GroupSummary<'a> {
+    /// The subgroup, `None` for the whole squad
+    subgroup:        Option<&'a str>,
+    players:         Vec<&'a str>,
+    hits:            PowerCondiHits,
+    #[serde(rename="bossHits")]
+    boss_hits:       PowerCondiHits,
+    #[serde(rename="enemyHits")]
+    enemy_hits:      PerTarget<PowerCondiHits>,
+    #[serde(rename="incomingDamage")]
+    incoming_damage: AbilityAndTotal,
+    #[serde(rename="incomingDamageBySource")]
+    incoming_skills: PerSource<Abilities>,
+    dispels:         Dispels,
+    /// Derived metrics, active time is the whole encounter
+    #[serde(rename="hitRates")]
+    hit_rates:       PowerCondiRates,
+    #[serde(rename="bossHitRates")]
+    boss_hit_rates:  PowerCondiRates,
+    #[serde(rename="bossPhaseHits")]
+    boss_phase_hits: Phased<PowerCondiHits>,
+}
+
+impl<'a> GroupSummary<'a> {
+    fn new<E: Event>(subgroup: Option<&'a str>, players: &[&PlayerSummary<'a, E>], duration: u64) -> Self {
+        let hits: PowerCondiHits      = merged(players.iter().map(|p| &p.hit_stats));
+        let boss_hits: PowerCondiHits = merged(players.iter().map(|p| &p.boss_hit_stats));
+
+        GroupSummary {
+            subgroup,
+            players:         players.iter().map(|p| p.agent.name()).collect(),
+            hit_rates:       hits.rates(duration, duration),
+            boss_hit_rates:  boss_hits.rates(duration, duration),
+            hits,
+            boss_hits,
+            boss_phase_hits: merged(players.iter().map(|p| &p.boss_phase_hits)),
+            enemy_hits:      merged(players.iter().map(|p| &p.enemy_hits)),
+            incoming_damage: merged(players.iter().map(|p| &p.incoming_damage)),
+            incoming_skills: merged(players.iter().map(|p| &p.incoming_skills)),
+            dispels:         merged(players.iter().map(|p| &p.dispels)),
+        }
     }
 }
 
 #[derive(Debug, Clone, Serialize)]
 struct BossSummary<'a> {
-    agent:  &'a Agent,
-    series: TimeSeries,
+    agent:           &'a Agent,
+    series:          TimeSeries,
+    invulnerability: Invulnerability,
+    /// Uptime and average stacks of the buffs and conditions on the boss over the encounter
+    buffs:           FnvHashMap<u16, BuffUptime>,
+    #[serde(rename="buffSeries")]
+    buff_series:     BuffSeries,
+    /// Time of death, server unix timestamp in milliseconds
+    #[serde(rename="diedAtUtc")]
+    died_at_utc:     Option<u64>,
+    /// Stacking of the buffs on the boss which are not in the buff table
+    #[serde(skip)]
+    observed:        Vec<(u16, StackMeta)>,
 }
 
 #[derive(Debug, Clone, Serialize)]
-struct EncounterInfo {
+struct EncounterInfo<'a> {
     #[serde(rename="logStart")]
     log_start:    u32,
     #[serde(rename="logEnd")]
     log_end:      u32,
+    #[serde(rename="logStartTimestamp")]
+    start_stamp:  Option<Timestamp>,
+    #[serde(rename="logEndTimestamp")]
+    end_stamp:    Option<Timestamp>,
     #[serde(rename="seriesStart")]
     series_start: u64,
     #[serde(rename="seriesEnd")]
     series_end:   u64,
+    /// Length of the encounter, milliseconds
+    duration:     u64,
+    /// The encounter split at the windows where the bosses are invulnerable
+    phases:       Vec<Encounter>,
     #[serde(rename="gameBuild")]
     game_build:   u64,
     #[serde(rename="lang")]
@@ -266,17 +438,77 @@ struct EncounterInfo {
     log_name:     String,
     boss:         Boss,
     success:      bool,
+    /// The agents which are considered targets, damage to these counts as boss damage
+    targets:      Vec<&'a Agent>,
 }
 
 #[derive(Serialize)]
 struct Data<'a, E: Event> {
-    encounter: EncounterInfo,
+    encounter: EncounterInfo<'a>,
     players:   Vec<PlayerSummary<'a, E::SourceEvent>>,
+    groups:    Vec<GroupSummary<'a>>,
+    squad:     GroupSummary<'a>,
     enemies:   Vec<BossSummary<'a>>,
-    buffs:     MetadataMap,
+    buffs:     BuffList,
     skills:    SkillList<'a>,
 }
 
+/// The buff table followed by the buffs which are not in the table, with the stacking observed
+/// in the log.
+struct BuffList {
+    observed: BTreeMap<u16, StackMeta>,
+}
+
+#[derive(Serialize)]
+struct ObservedBuff {
+    #[serde(rename="skillId")]
+    skill_id: u16,
+    stack:    StackMeta,
+}
+
+impl BuffList {
+    /// Keeps the most stacks observed on any agent for each buff.
+    fn new<I: IntoIterator<Item=(u16, StackMeta)>>(observed: I) -> Self {
+        let mut map = BTreeMap::new();
+
+        for (skill, meta) in observed {
+            let m = map.entry(skill).or_insert(meta);
+
+            if let (StackMeta::Observed { max: a }, StackMeta::Observed { max: b }) = (*m, meta) {
+                if b > a {
+                    *m = meta;
+                }
+            }
+        }
+
+        BuffList { observed: map }
+    }
+}
+
+impl Serialize for BuffList {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+        where S: Serializer {
+        let mut map = serializer.serialize_map(Some(buffs::META_LIST.len() + self.observed.len()))?;
+
+        for m in buffs::META_LIST {
+            map.serialize_entry(&m.skill_id, m)?;
+        }
+
+        for (&skill_id, &stack) in &self.observed {
+            map.serialize_entry(&skill_id, &ObservedBuff { skill_id, stack })?;
+        }
+
+        map.end()
+    }
+}
+
+/// Time of death of the agent as a server unix timestamp in milliseconds.
+fn died_at_utc(meta: &Metadata, agent: &Agent) -> Option<u64> {
+    let timestamp = meta.log_start_timestamp().or(meta.log_end_timestamp());
+
+    agent.died().and_then(|t| timestamp.map(|ts| ts.to_unix_millis(t)))
+}
+
 fn group_agents_by_species<'a, I: Iterator<Item=&'a Agent>>(iter: I) -> FnvHashMap<SpeciesId, Vec<&'a Agent>> {
     let mut map = FnvHashMap::default();
 
@@ -287,47 +519,177 @@ fn group_agents_by_species<'a, I: Iterator<Item=&'a Agent>>(iter: I) -> FnvHashM
     map
 }
 
-pub fn parse_data<W: Write>(buffer: &[u8], logname: String, pretty:bool, writer: W) -> Result<(), JSONError> {
-    let evtc = raw::transmute(buffer);
-    let meta = Metadata::new(&evtc);
+/// Options for [parse_data], the defaults match the defaults of the CLI.
+#[derive(Default)]
+pub struct Options {
+    /// If to pretty-print the JSON
+    pub pretty:  bool,
+    /// The agents to count as boss damage
+    pub targets: TargetSet,
+    /// When the encounter starts
+    pub start:   EncounterStart,
+    /// When the encounter ends
+    pub end:     EncounterEnd,
+}
+
+pub fn parse_data<W: Write>(buffer: &[u8], logname: String, options: &Options, writer: W) -> Result<(), JSONError> {
+    let evtc     = raw::transmute(buffer);
+    let mut meta = Metadata::new(&evtc);
+
+    // Set before anything else, the statistics and the time-series all use the encounter of meta
+    let encounter = Encounter::new(&meta, options.start, options.end);
+
+    meta.set_encounter(encounter);
 
+    let targets: Vec<_> = meta.targets(&options.targets).collect();
     let bosses:  Vec<_> = meta.bosses().collect();
-    let boss_ids: Vec<_> = bosses.iter().map(|a| a.id()).collect();
-
-    let player_summaries = meta.agents()
-                               .iter()
-                               .filter(|a| a.profession().is_player_character())
-                               .map(|a| PlayerSummary::new(&meta, a)
-                                        // TODO: Is related to enough to get everything?
-                                        .parse(&boss_ids[..], meta.encounter_events().related_to(a))
-                                        .finalize(meta.log_end()))
-                               .collect();
-
-    let boss_summaries: Vec<_> = meta.bosses().map(|b| BossSummary {
-        agent: b,
-        series: TimeSeries::parse_agent(&meta, b),
-    }).collect();
+
+    let players: Vec<_> = meta.agents().iter().filter(|a| a.profession().is_player_character()).collect();
+
+    // TODO: Is related to enough to get everything?
+    let routes: Vec<_> = players.iter().map(|&a| (a, Route::Related))
+                                .chain(bosses.iter().map(|&a| (a, Route::Related)))
+                                .collect();
+
+    let mut invulns: Vec<_> = bosses.iter().map(|&b| Invulnerability::new(b, meta.encounter())).collect();
+
+    // Buffs active before logging started, these precede the encounter
+    let initial = initial_buffs(meta.events());
+
+    let mut parts: Vec<Vec<_>> = routes.iter().map(|_| Vec::new()).collect();
+
+    {
+        let mut dispatcher = Dispatcher::new();
+        let invulns        = &mut invulns;
+
+        dispatcher.add_global(move |e: &CombatEventV1| if let Some(b) = e.into_buff() {
+            for i in invulns.iter_mut() {
+                i.add_event(b.clone());
+            }
+        });
+
+        for (&(agent, route), part) in routes.iter().zip(parts.iter_mut()) {
+            dispatcher.add_agent(agent, route, move |e| part.push(e));
+
+            // Strips and cleanses by gadgets/minions target them instead of the agent
+            for g in meta.agents_for_master(agent) {
+                dispatcher.add_gadget(agent, g);
+            }
+        }
+
+        dispatcher.run(meta.encounter_events());
+    }
+
+    for i in &mut invulns {
+        i.finalize();
+    }
+
+    let phases = phase::phases(meta.encounter(), &invulns);
+
+    let boss_parts = parts.split_off(players.len());
+
+    let mut player_summaries: Vec<_> = dispatch::map_partitions(parts, |i, events| {
+        let mut summary = PlayerSummary::new(&meta, players[i], &targets[..], &phases);
+
+        summary.buffs.seed(&initial, meta.encounter().start());
+
+        for e in events {
+            summary.add_event(e);
+        }
+
+        summary.finalize(meta.encounter().end());
+
+        summary
+    });
+
+    let boss_summaries: Vec<_> = dispatch::map_partitions(boss_parts, |i, events| {
+        let boss       = bosses[i];
+        let mut series = TimeSeries::new(&meta);
+        let mut buffs  = buffs::Map::new(boss.id());
+        let mut uptime = BuffSeries::new(meta.encounter().start(), 1000);
+
+        buffs.seed(&initial, meta.encounter().start());
+
+        for e in events {
+            match e.classify() {
+                Some(EventKind::BuffApply { time, .. }) | Some(EventKind::BuffRemove { time, .. }) => if let Some(b) = e.clone().into_buff() {
+                    uptime.update(&mut buffs, time);
+                    buffs.add_event(b);
+                },
+                _ => {},
+            }
+
+            if let Some(e) = e.from_agent_or_gadgets(boss.id(), boss.instance_id()) {
+                series.add_event(e, &meta);
+            }
+        }
+
+        series.finalize();
+        uptime.finalize(&mut buffs, meta.encounter().end());
+
+        BossSummary {
+            agent:           boss,
+            series,
+            invulnerability: invulns[i].clone(),
+            buffs:           buffs.uptimes(meta.encounter().duration_ms()).collect(),
+            buff_series:     uptime,
+            died_at_utc:     died_at_utc(&meta, boss),
+            observed:        buffs.observed().collect(),
+        }
+    });
+
+    let generation: Vec<_> = {
+        let targets: Vec<_> = player_summaries.iter().map(|p| (p.agent, &p.buffs)).collect();
+
+        player_summaries.iter().map(|p| {
+            let sources: Vec<_> = iter::once(p.agent.id()).chain(meta.agents_for_master(p.agent).map(|a| a.id())).collect();
+
+            Generation::new(p.agent, &sources, &targets, meta.encounter().duration_ms())
+        }).collect()
+    };
+
+    for (p, g) in player_summaries.iter_mut().zip(generation) {
+        p.generation = g;
+    }
+
+    let mut subgroups: Vec<_> = player_summaries.iter().map(|p| p.agent.subgroup()).collect();
+
+    subgroups.sort();
+    subgroups.dedup();
+
+    let group_summaries = subgroups.into_iter().map(|g| GroupSummary::new(Some(g), &player_summaries.iter().filter(|p| p.agent.subgroup() == g).collect::<Vec<_>>(), meta.encounter().duration_ms())).collect();
+    let squad_summary   = GroupSummary::new(None, &player_summaries.iter().collect::<Vec<_>>(), meta.encounter().duration_ms());
+
+    let buff_list = BuffList::new(player_summaries.iter().flat_map(|p| p.buffs.observed())
+                                                  .chain(boss_summaries.iter().flat_map(|b| b.observed.iter().cloned())));
 
     let data: Data<&CombatEventV1> = Data {
         encounter: EncounterInfo {
             log_start:    meta.log_start_time(),
             log_end:      meta.log_end_time(),
-            series_start: meta.log_start(),
-            series_end:   meta.log_end(),
+            start_stamp:  meta.log_start_timestamp(),
+            end_stamp:    meta.log_end_timestamp(),
+            series_start: meta.encounter().start(),
+            series_end:   meta.encounter().end(),
+            duration:     meta.encounter().duration_ms(),
+            phases,
             log_name:     logname,
             game_build:   meta.game_build(),
             game_lang:    meta.language(),
             server_shard: meta.server_shard(),
             boss:         meta.boss(),
             success:      meta.bosses().fold(true, |a, b| a && b.did_die()),
+            targets,
         },
         players:   player_summaries,
+        groups:    group_summaries,
+        squad:     squad_summary,
         enemies:   boss_summaries,
-        buffs:     buffs::META_MAP,
+        buffs:     buff_list,
         skills:    meta.skill_list(),
     };
 
-    if pretty {
+    if options.pretty {
         serde_json::to_writer_pretty(writer, &data)
     }
     else {
@@ -1,9 +1,9 @@
 extern crate clap;
-#[macro_use]
 extern crate evtc;
 extern crate fnv;
 extern crate memmap;
 extern crate regex;
+extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate serde_json;
@@ -14,6 +14,12 @@ mod json;
 use clap::App;
 use clap::Arg;
 
+use evtc::AgentId;
+use evtc::EncounterEnd;
+use evtc::EncounterStart;
+use evtc::SpeciesId;
+use evtc::TargetSet;
+
 use regex::Regex;
 
 use std::borrow::Cow;
@@ -45,6 +51,27 @@ fn main() {
         .arg(Arg::with_name("pretty")
             .short("p")
             .help("If to pretty-print the JSON"))
+        .arg(Arg::with_name("target")
+            .short("t")
+            .long("target")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .validator(|s| parse_target(&s).map(|_| ()))
+            .help("Agents to count as boss damage: \"bosses\", a species id (1234 or &1234) or an agent id (#1234), defaults to the bosses"))
+        // The defaults of start and end match EncounterStart::default() and EncounterEnd::default()
+        .arg(Arg::with_name("start")
+            .long("start")
+            .takes_value(true)
+            .possible_values(&["first-aware", "enter-combat", "first-boss-damage", "log-start"])
+            .default_value("enter-combat")
+            .help("When the encounter starts, falls back to when the bosses were first observed"))
+        .arg(Arg::with_name("end")
+            .long("end")
+            .takes_value(true)
+            .possible_values(&["last-aware", "boss-death", "log-end"])
+            .default_value("boss-death")
+            .help("When the encounter ends, falls back to when the bosses were last observed"))
         .get_matches();
 
     let is_json  = matches.occurrences_of("json") > 0;
@@ -55,7 +82,23 @@ fn main() {
         .into_owned();
     let file    = File::open(&name).expect("could not open file");
     let mut out = BufWriter::new(File::create(&out_name).expect("Coult not create file"));
-    let pretty  = matches.occurrences_of("pretty") > 0;
+    let options = json::Options {
+        pretty:  matches.occurrences_of("pretty") > 0,
+        targets: matches.values_of("target")
+            .map(|v| TargetSet::Any(v.map(|s| parse_target(s).unwrap()).collect()))
+            .unwrap_or_default(),
+        start:   match matches.value_of("start").unwrap() {
+            "first-aware"       => EncounterStart::FirstAware,
+            "first-boss-damage" => EncounterStart::FirstBossDamage,
+            "log-start"         => EncounterStart::LogStart,
+            _                   => EncounterStart::EnterCombat,
+        },
+        end:     match matches.value_of("end").unwrap() {
+            "last-aware" => EncounterEnd::LastAware,
+            "log-end"    => EncounterEnd::LogEnd,
+            _            => EncounterEnd::BossDeath,
+        },
+    };
 
     if name.ends_with(".zip") {
         use std::io::Read;
@@ -67,24 +110,36 @@ fn main() {
         file.read_to_end(&mut buffer).expect("Failed to read first file in arcive");
 
         if is_json {
-            json::parse_data(&buffer[..], name, pretty, out).unwrap();
+            json::parse_data(&buffer[..], name, &options, out).unwrap();
         }
         else {
-            wrap_html(&mut out, |out| json::parse_data(&buffer[..], name, pretty, out)).unwrap();
+            wrap_html(&mut out, |out| json::parse_data(&buffer[..], name, &options, out)).unwrap();
         }
     }
     else {
         let mmap = unsafe { memmap::Mmap::map(&file).expect("Failed to mmap() file") };
 
         if is_json {
-            json::parse_data(&mmap[..], name, pretty, out).unwrap();
+            json::parse_data(&mmap[..], name, &options, out).unwrap();
         }
         else {
-            wrap_html(&mut out, |out| json::parse_data(&mmap[..], name, pretty, out)).unwrap();
+            wrap_html(&mut out, |out| json::parse_data(&mmap[..], name, &options, out)).unwrap();
         }
     }
 }
 
+/// Parses a target specification, the prefixes match the `Display` implementations of the ids.
+fn parse_target(s: &str) -> Result<TargetSet, String> {
+    let err = |_| format!("Invalid target {:?}, expected \"bosses\", a species id or an agent id", s);
+
+    match s {
+        "bosses"                => Ok(TargetSet::Bosses),
+        s if s.starts_with('#') => s[1..].parse().map(|id| TargetSet::Agents(vec![AgentId::new(id)])).map_err(err),
+        s if s.starts_with('&') => s[1..].parse().map(|id| TargetSet::Species(vec![SpeciesId::new(id)])).map_err(err),
+        s                       => s.parse().map(|id| TargetSet::Species(vec![SpeciesId::new(id)])).map_err(err),
+    }
+}
+
 fn wrap_html<'a, W: Write, E: Error + 'static, F: FnOnce(&mut W) -> Result<(), E>>(out: &'a mut W, f: F) -> Result<usize, Box<Error>> {
     out.write(&b"<html>
   <head>
@@ -1,12 +1,23 @@
 use event::BuffRemoval;
 use event::Buff;
+use event::Event;
+use event::Source;
+use event::StateChange;
 
+use Agent;
 use AgentId;
+use Profession;
+
+use fnv::FnvHashMap;
 
 use serde::ser::Serialize;
 use serde::ser::Serializer;
 use serde::ser::SerializeMap;
 
+use std::cmp;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::marker::PhantomData;
 use std::mem;
 use std::fmt;
 
@@ -14,16 +25,57 @@ pub trait Stack: Default {
     /// The stack metadata
     const STACK_META: StackMeta;
 
-    /// Pushes a new stack with the supplied duration in milliseconds, overstack is returned.
-    fn push(&mut self, u32) -> u32;
-    /// Updates the stack with the new timestamp difference, milliseconds.
-    fn update(&mut self, u32);
+    /// Pushes a new stack with the supplied duration in milliseconds from the source with the
+    /// supplied index, the overstack and the source of the overstacked stack are returned.
+    fn push(&mut self, duration: u32, source: u32) -> (u32, u32);
+    /// Updates the stack with the new timestamp difference, milliseconds, returns the time the
+    /// stacks were active during the difference.
+    fn update(&mut self, u32) -> Elapsed;
+    /// Removes the single stack with the duration closest to the supplied duration in
+    /// milliseconds, the remaining duration and the source of the removed stack are returned.
+    ///
+    /// Only stacks within [REMOVAL_TOLERANCE] of the duration are considered, if there are none
+    /// nothing is removed and a zero duration is returned. If a shorter and a longer stack are
+    /// equally close the longer stack is removed.
+    fn remove(&mut self, duration: u32) -> (u32, u32);
     /// The total duration in milliseconds.
     fn sum(&self) -> u32;
     /// Clears the stack.
     fn clear(&mut self);
     /// The number of stacks present.
     fn stacks(&self) -> usize;
+    /// The remaining duration in milliseconds and the source of each stack.
+    fn remaining(&self) -> Vec<(u32, u32)>;
+    /// The stack metadata, including anything inferred from the applied stacks.
+    #[inline]
+    fn stack_meta(&self) -> StackMeta {
+        Self::STACK_META
+    }
+}
+
+/// Time the stacks were active during an update.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct Elapsed {
+    /// Time with at least one active stack, milliseconds.
+    pub active: u32,
+    /// Sum of the time each stack was active, milliseconds.
+    pub stacks: u64,
+}
+
+/// Largest difference between the duration of a buff removal and the remaining duration of the
+/// stack it removed, milliseconds.
+///
+/// The remaining duration is only updated once per server tick (40 ms) so the durations rarely
+/// match exactly. Removals with a duration below the tolerance are stacks expiring naturally.
+pub const REMOVAL_TOLERANCE: u32 = 50;
+
+/// Index of the active stack with the duration closest to `duration`, the longer on ties.
+fn closest<I: IntoIterator<Item=u32>>(stacks: I, duration: u32) -> Option<usize> {
+    stacks.into_iter()
+          .enumerate()
+          .filter(|&(_, d)| d > 0 && d.abs_diff(duration) <= REMOVAL_TOLERANCE)
+          .min_by_key(|&(_, d)| (d.abs_diff(duration), Reverse(d)))
+          .map(|(i, _)| i)
 }
 
 pub trait StackType {
@@ -62,43 +114,61 @@ impl StackType for Replace {
 ///
 /// This implies that the current stack is not replaced, even if it is smaller than the
 /// new stack.
+///
+/// The second field holds the durations and the third the source of each stack.
 #[derive(Debug, Clone)]
-pub struct Duration<T: StackType, U: Sized>(pub T, pub U);
+pub struct Duration<T: StackType, U: Sized>(pub T, pub U, pub U);
 
+/// Wrapper for an intensity-stacking stack, the second field holds the durations and the third
+/// the source of each stack.
 #[derive(Debug, Clone)]
-pub struct Intensity<T: StackType, U: Sized>(pub T, pub U);
+pub struct Intensity<T: StackType, U: Sized>(pub T, pub U, pub U);
 
 macro_rules! impl_intensity {
     ($t:ident, $n:expr) => {
 impl Default for Intensity<$t, [u32; $n]> {
     fn default() -> Self {
-        Intensity($t, [0; $n])
+        Intensity($t, [0; $n], [0; $n])
     }
 }
 
 impl Stack for Intensity<$t, [u32; $n]> {
     const STACK_META: StackMeta = StackMeta::Intensity { max: $n };
 
-    fn push(&mut self, mut stack: u32) -> u32 {
-        for (i, s) in self.1.iter_mut().enumerate() {
+    fn push(&mut self, mut stack: u32, mut source: u32) -> (u32, u32) {
+        for (i, (s, src)) in self.1.iter_mut().zip(self.2.iter_mut()).enumerate() {
             // Add if the stack is empty or if we are above the locked items and it is lower
             if $t::can_replace(i, *s, stack) {
                 mem::swap(&mut stack, s);
+                mem::swap(&mut source, src);
             }
 
             if stack == 0 {
-                return 0;
+                return (0, source);
             }
         }
 
         // Return the overstack
-        stack
+        (stack, source)
     }
 
-    fn update(&mut self, time: u32) {
-        for d in self.1.iter_mut() {
-            *d = d.saturating_sub(time);
+    fn update(&mut self, time: u32) -> Elapsed {
+        let mut elapsed = Elapsed::default();
+
+        for d in self.1.iter_mut().filter(|d| **d > 0) {
+            let t = cmp::min(*d, time);
+
+            elapsed.active  = cmp::max(elapsed.active, t);
+            elapsed.stacks += t as u64;
+
+            *d -= t;
         }
+
+        elapsed
+    }
+
+    fn remove(&mut self, duration: u32) -> (u32, u32) {
+        closest(self.1.iter().cloned(), duration).map(|i| (mem::replace(&mut self.1[i], 0), self.2[i])).unwrap_or((0, 0))
     }
 
     fn sum(&self) -> u32 {
@@ -109,6 +179,10 @@ impl Stack for Intensity<$t, [u32; $n]> {
         self.1.iter().filter(|&&i| i > 0).count()
     }
 
+    fn remaining(&self) -> Vec<(u32, u32)> {
+        self.1.iter().cloned().zip(self.2.iter().cloned()).filter(|&(d, _)| d > 0).collect()
+    }
+
     fn clear(&mut self) {
         for d in self.1.iter_mut() {
             *d = 0;
@@ -122,30 +196,33 @@ macro_rules! impl_duration /*_current_duration*/ {
     ($t:ident, $n:expr) => {
 impl Default for Duration<$t, [u32; $n]> {
     fn default() -> Self {
-        Duration($t, [0; $n])
+        Duration($t, [0; $n], [0; $n])
     }
 }
 
 impl Stack for Duration<$t, [u32; $n]> {
-    const STACK_META: StackMeta = StackMeta::Duration;
+    const STACK_META: StackMeta = StackMeta::Duration { max: $n };
 
-    fn push(&mut self, mut stack: u32) -> u32 {
-        for (i, s) in self.1.iter_mut().enumerate() {
+    fn push(&mut self, mut stack: u32, mut source: u32) -> (u32, u32) {
+        for (i, (s, src)) in self.1.iter_mut().zip(self.2.iter_mut()).enumerate() {
             // Add if the stack is empty or if we are above the locked items and it is lower
             if $t::can_replace(i, *s, stack) { // *s == 0 || (i >= $lock && *s < stack) {
                 mem::swap(&mut stack, s);
+                mem::swap(&mut source, src);
             }
 
             if stack == 0 {
-                return 0;
+                return (0, source);
             }
         }
 
         // Return the overstack
-        stack
+        (stack, source)
     }
 
-    fn update(&mut self, mut time: u32) {
+    fn update(&mut self, mut time: u32) -> Elapsed {
+        let start = time;
+
         while time > 0 && self.1[0] > 0 {
             if self.1[0] > time {
                 self.1[0] -= time;
@@ -162,10 +239,36 @@ impl Stack for Duration<$t, [u32; $n]> {
                 for i in 0..($n - 1) {
                     if self.1[i] < self.1[i + 1] {
                         self.1.swap(i, i + 1);
+                        self.2.swap(i, i + 1);
                     }
                 }
             }
         }
+
+        // Only the first stack is ticking
+        Elapsed {
+            active: start - time,
+            stacks: (start - time) as u64,
+        }
+    }
+
+    fn remove(&mut self, duration: u32) -> (u32, u32) {
+        match closest(self.1.iter().cloned(), duration) {
+            Some(i) => {
+                let removed = (self.1[i], self.2[i]);
+
+                // Keep the active stacks first
+                for j in i..($n - 1) {
+                    self.1[j] = self.1[j + 1];
+                    self.2[j] = self.2[j + 1];
+                }
+
+                self.1[$n - 1] = 0;
+
+                removed
+            },
+            None => (0, 0),
+        }
     }
 
     fn sum(&self) -> u32 {
@@ -182,6 +285,10 @@ impl Stack for Duration<$t, [u32; $n]> {
         return $n;
     }
 
+    fn remaining(&self) -> Vec<(u32, u32)> {
+        self.1.iter().cloned().zip(self.2.iter().cloned()).take_while(|&(d, _)| d > 0).collect()
+    }
+
     fn clear(&mut self) {
         for d in self.1.iter_mut() {
             *d = 0;
@@ -191,12 +298,216 @@ impl Stack for Duration<$t, [u32; $n]> {
     }
 }
 
+/// Intensity-stack without a fixed capacity, used for buffs which are not in the buff table.
+///
+/// Every application is kept as its own stack and the most stacks seen at once is recorded, which
+/// approximates the stacking model of the buff.
+#[derive(Debug, Clone, Default)]
+pub struct Observed {
+    /// Remaining duration and source of each stack.
+    stacks: Vec<(u32, u32)>,
+    max:    usize,
+}
+
+impl Observed {
+    /// The most stacks which were active at the same time.
+    #[inline]
+    pub fn max_stacks(&self) -> usize {
+        self.max
+    }
+}
+
+impl Stack for Observed {
+    const STACK_META: StackMeta = StackMeta::Observed { max: 0 };
+
+    fn push(&mut self, stack: u32, source: u32) -> (u32, u32) {
+        if stack > 0 {
+            self.stacks.push((stack, source));
+
+            self.max = cmp::max(self.max, self.stacks.len());
+        }
+
+        // No known cap, so nothing is overstacked
+        (0, source)
+    }
+
+    fn update(&mut self, time: u32) -> Elapsed {
+        let mut elapsed = Elapsed::default();
+
+        for &mut (ref mut d, _) in self.stacks.iter_mut() {
+            let t = cmp::min(*d, time);
+
+            elapsed.active  = cmp::max(elapsed.active, t);
+            elapsed.stacks += t as u64;
+
+            *d -= t;
+        }
+
+        self.stacks.retain(|&(d, _)| d > 0);
+
+        elapsed
+    }
+
+    fn remove(&mut self, duration: u32) -> (u32, u32) {
+        closest(self.stacks.iter().map(|s| s.0), duration).map(|i| self.stacks.swap_remove(i)).unwrap_or((0, 0))
+    }
+
+    fn sum(&self) -> u32 {
+        self.stacks.iter().map(|s| s.0).sum()
+    }
+
+    fn stacks(&self) -> usize {
+        self.stacks.len()
+    }
+
+    fn remaining(&self) -> Vec<(u32, u32)> {
+        self.stacks.clone()
+    }
+
+    fn clear(&mut self) {
+        self.stacks.clear();
+    }
+
+    fn stack_meta(&self) -> StackMeta {
+        StackMeta::Observed { max: cmp::min(self.max, u16::MAX as usize) as u16 }
+    }
+}
+
+/// Intensity-stack storing the absolute expiry time of each stack in a heap, behaves like
+/// `Intensity<Replace, [u32; N]>`.
+///
+/// Updates only touch the stacks which expire during the update, which makes it a lot cheaper
+/// than the array stacks for buffs with many concurrent stacks like bleeding and vulnerability.
+#[derive(Debug, Clone)]
+pub struct Expiry<T: StackType, U: Sized> {
+    /// Time since the stack was created, milliseconds.
+    now:    u64,
+    /// Expiry time on the same clock as `now` and source of each active stack, the first to
+    /// expire on top.
+    heap:   BinaryHeap<Reverse<(u64, u32)>>,
+    marker: PhantomData<(T, U)>,
+}
+
+impl<T: StackType, U: Sized> Expiry<T, U> {
+    /// Remaining duration of the stack expiring at `expiry`.
+    #[inline]
+    fn time_left(&self, expiry: u64) -> u32 {
+        (expiry - self.now) as u32
+    }
+}
+
+macro_rules! impl_expiry {
+    ($n:expr) => {
+impl Default for Expiry<Replace, [u32; $n]> {
+    fn default() -> Self {
+        Expiry {
+            now:    0,
+            heap:   BinaryHeap::with_capacity(cmp::min($n, 64)),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl Stack for Expiry<Replace, [u32; $n]> {
+    const STACK_META: StackMeta = StackMeta::Intensity { max: $n };
+
+    fn push(&mut self, stack: u32, source: u32) -> (u32, u32) {
+        if stack == 0 {
+            return (0, source);
+        }
+
+        if self.heap.len() < $n {
+            self.heap.push(Reverse((self.now + stack as u64, source)));
+
+            return (0, source);
+        }
+
+        // Full, replace the shortest stack if the new one is longer
+        let shortest = self.heap.peek().map(|r| (self.time_left((r.0).0), (r.0).1)).unwrap_or((0, source));
+
+        if shortest.0 < stack {
+            self.heap.pop();
+            self.heap.push(Reverse((self.now + stack as u64, source)));
+
+            shortest
+        }
+        else {
+            (stack, source)
+        }
+    }
+
+    fn update(&mut self, time: u32) -> Elapsed {
+        let end         = self.now + time as u64;
+        let mut elapsed = Elapsed::default();
+
+        while let Some(&Reverse((expiry, _))) = self.heap.peek() {
+            if expiry > end {
+                break;
+            }
+
+            let t = self.time_left(expiry);
+
+            elapsed.active  = cmp::max(elapsed.active, t);
+            elapsed.stacks += t as u64;
+
+            self.heap.pop();
+        }
+
+        // The remaining stacks were active during the whole update
+        if ! self.heap.is_empty() {
+            elapsed.active  = time;
+            elapsed.stacks += self.heap.len() as u64 * time as u64;
+        }
+
+        self.now = end;
+
+        elapsed
+    }
+
+    fn remove(&mut self, duration: u32) -> (u32, u32) {
+        let target = self.now + duration as u64;
+        let mut v  = mem::take(&mut self.heap).into_vec();
+
+        let closest = v.iter()
+                       .enumerate()
+                       .filter(|&(_, r)| (r.0).0.abs_diff(target) <= REMOVAL_TOLERANCE as u64)
+                       .min_by_key(|&(_, r)| ((r.0).0.abs_diff(target), Reverse((r.0).0)))
+                       .map(|(i, _)| i);
+
+        let removed = closest.map(|i| v.swap_remove(i).0).map(|(e, s)| (self.time_left(e), s)).unwrap_or((0, 0));
+
+        self.heap = BinaryHeap::from(v);
+
+        removed
+    }
+
+    fn sum(&self) -> u32 {
+        self.heap.iter().map(|r| self.time_left((r.0).0)).sum()
+    }
+
+    fn stacks(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn remaining(&self) -> Vec<(u32, u32)> {
+        self.heap.iter().map(|r| (self.time_left((r.0).0), (r.0).1)).collect()
+    }
+
+    fn clear(&mut self) {
+        self.heap.clear();
+    }
+}
+    }
+}
+
 impl_duration!(Replace, 1);
 impl_duration!(Queue, 5);
 impl_intensity!(Replace, 1);
 impl_intensity!(Replace, 25);
 impl_intensity!(Queue,   25);
 impl_intensity!(Replace, 1500);
+impl_expiry!(25);
+impl_expiry!(1500);
 
 #[derive(Clone, Serialize)]
 pub struct Simulator<T: Stack> {
@@ -216,19 +527,32 @@ pub struct Simulator<T: Stack> {
     overstack: u32,
     /// Sum of duration stripped, milliseconds.
     stripped:  u32,
+    /// Time with at least one active stack, milliseconds.
+    active:    u64,
+    /// Sum of the time each stack was active, milliseconds.
+    #[serde(rename="stackTime")]
+    stacked:   u64,
+    /// Duration which took effect per source agent, milliseconds.
+    #[serde(skip)]
+    generated: FnvHashMap<AgentId, u64>,
+    /// The source agents, the stacks refer to them by index.
+    #[serde(skip)]
+    sources:   Vec<AgentId>,
 }
 
 impl<T: Stack> fmt::Debug for Simulator<T> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
-            "Simulator<{}> {{ stack: SKIPPED, time: {time:?}, agent: {agent:?}, uptime: {uptime:?}, overstack: {overstack:?}, stripped: {stripped:?} }}",
+            "Simulator<{}> {{ stack: SKIPPED, time: {time:?}, agent: {agent:?}, uptime: {uptime:?}, overstack: {overstack:?}, stripped: {stripped:?}, active: {active:?}, stacked: {stacked:?} }}",
             stringify!(T),
             time=self.time,
             agent=self.agent,
             uptime=self.uptime,
             overstack=self.overstack,
-            stripped=self.stripped
+            stripped=self.stripped,
+            active=self.active,
+            stacked=self.stacked
         )
     }
 }
@@ -242,13 +566,44 @@ impl<T: Stack> Simulator<T> {
             uptime:    0,
             overstack: 0,
             stripped:  0,
+            active:    0,
+            stacked:   0,
+            generated: FnvHashMap::default(),
+            sources:   Vec::new(),
             agent,
         }
     }
 
+    /// Index of the source agent for the stacks.
+    #[inline]
+    fn source(&mut self, agent: AgentId) -> u32 {
+        match self.sources.iter().position(|&a| a == agent) {
+            Some(i) => i as u32,
+            None    => {
+                self.sources.push(agent);
+
+                (self.sources.len() - 1) as u32
+            },
+        }
+    }
+
+    /// Subtracts duration which did not take effect from the generation of the source of the
+    /// stack.
+    #[inline]
+    fn revoke(&mut self, source: u32, duration: u32) {
+        let generated = &mut self.generated;
+
+        if let Some(g) = self.sources.get(source as usize).and_then(|a| generated.get_mut(a)) {
+            *g = g.saturating_sub(duration as u64);
+        }
+    }
+
     #[inline]
     pub fn update(&mut self, time: u64) {
-        self.stack.update(time.saturating_sub(self.time) as u32);
+        let elapsed = self.stack.update(time.saturating_sub(self.time) as u32);
+
+        self.active  += elapsed.active as u64;
+        self.stacked += elapsed.stacks;
 
         self.time = time;
     }
@@ -258,28 +613,84 @@ impl<T: Stack> Simulator<T> {
         match e.removal() {
             // If we are not a buff-removal, the target receives the boon
             BuffRemoval::None   => if e.target_agent() == self.agent {
-                let over = self.stack.push(e.duration());
+                let source            = self.source(e.agent());
+                let (over, displaced) = self.stack.push(e.duration(), source);
 
                 self.uptime    += e.duration().saturating_sub(over); // e.overstack());
                 self.overstack += over; // e.overstack();
+
+                // The overstack is either a part of the new stack or of the stack it replaced
+                *self.generated.entry(e.agent()).or_default() += e.duration() as u64;
+
+                self.revoke(displaced, over);
             },
 
-            // If we are a buff-removal, it is the source which has the boon removed
-            BuffRemoval::All | BuffRemoval::Single | BuffRemoval::Manual => if e.agent() == self.agent {
-                let sum = self.stack.sum();
+            // If we are a buff-removal, it is the source which has the boon removed and the target
+            // which caused it, see statistics::Dispels for removals per causing agent
+            removal => if e.agent() == self.agent {
+                // The stacks expiring by themselves have already been removed by the update
+                let expired = e.duration() <= REMOVAL_TOLERANCE;
+
+                let removed = match removal {
+                    BuffRemoval::All => {
+                        for (d, source) in self.stack.remaining() {
+                            self.revoke(source, d);
+                        }
 
-                self.uptime    = if sum > self.uptime { 0 } else { self.uptime - sum };
-                self.stripped += sum;
+                        let sum = self.stack.sum();
 
-                self.stack.clear();
+                        self.stack.clear();
+
+                        sum
+                    },
+                    _ if expired => 0,
+                    _            => {
+                        let (d, source) = self.stack.remove(e.duration());
+
+                        self.revoke(source, d);
+
+                        d
+                    },
+                };
+
+                self.uptime = self.uptime.saturating_sub(removed);
+
+                // Manual removals are caused by leaving combat or stacks expiring when at max
+                // stacks, not by any agent
+                if removal != BuffRemoval::Manual && ! expired {
+                    self.stripped += removed;
+                }
             },
         }
     }
 
+    /// Pushes a stack from `source` which was already active when the log started, the
+    /// simulator should be updated to the time of the seeding first.
+    #[inline]
+    pub fn add_initial(&mut self, duration: u32, source: AgentId) {
+        let index             = self.source(source);
+        let (over, displaced) = self.stack.push(duration, index);
+
+        self.uptime    += duration.saturating_sub(over);
+        self.overstack += over;
+
+        // Nothing to credit if the source is unknown
+        if source != AgentId::empty() {
+            *self.generated.entry(source).or_default() += duration as u64;
+        }
+
+        self.revoke(displaced, over);
+    }
+
     /// Performs a final update and then subtracts the remaining stack duration from the uptime
+    /// and the generation of the sources.
     #[inline]
     pub fn finalize(&mut self, time: u64) {
-        self.stack.update(time.saturating_sub(self.time) as u32);
+        self.update(time);
+
+        for (d, source) in self.stack.remaining() {
+            self.revoke(source, d);
+        }
 
         self.uptime = self.uptime.saturating_sub(self.stack.sum());
     }
@@ -289,6 +700,11 @@ impl<T: Stack> Simulator<T> {
         self.stack.stacks()
     }
 
+    #[inline]
+    pub fn stack_meta(&self) -> StackMeta {
+        self.stack.stack_meta()
+    }
+
     #[inline]
     pub fn sum(&self) -> u32 {
         self.stack.sum()
@@ -308,39 +724,89 @@ impl<T: Stack> Simulator<T> {
     pub fn stripped(&self) -> u32 {
         self.stripped
     }
+
+    /// Time with at least one active stack, milliseconds.
+    #[inline]
+    pub fn active(&self) -> u64 {
+        self.active
+    }
+
+    /// Sum of the time each stack was active, milliseconds.
+    #[inline]
+    pub fn stack_time(&self) -> u64 {
+        self.stacked
+    }
+
+    /// Duration which took effect per source agent, milliseconds; the applied duration minus
+    /// overstack, removed stacks and the stacks remaining when finalized.
+    #[inline]
+    pub fn generated(&self) -> &FnvHashMap<AgentId, u64> {
+        &self.generated
+    }
 }
 
 pub trait BoxedSimulator<E: Buff> {
     fn update(&mut self, time: u64);
     fn add_event(&mut self, e: E);
+    fn add_initial(&mut self, duration: u32, source: AgentId);
     fn stacks(&self) -> usize;
+    fn stack_meta(&self) -> StackMeta;
     fn sum(&self) -> u32;
     fn uptime(&self) -> u32;
     fn finalize(&mut self, u64);
     fn overstack(&self) -> u32;
     fn stripped(&self) -> u32;
+    fn active(&self) -> u64;
+    fn stack_time(&self) -> u64;
+    fn generated(&self) -> Option<&FnvHashMap<AgentId, u64>>;
 }
 
 impl<T: Stack, E:Buff> BoxedSimulator<E> for Simulator<T> {
     fn update(&mut self, time: u64) { Simulator::update(self, time) }
     fn add_event(&mut self, e: E) { Simulator::add_event(self, e) }
+    fn add_initial(&mut self, duration: u32, source: AgentId) { Simulator::add_initial(self, duration, source) }
     fn stacks(&self) -> usize { Simulator::stacks(self) }
+    fn stack_meta(&self) -> StackMeta { Simulator::stack_meta(self) }
     fn sum(&self) -> u32 { Simulator::sum(self) }
     fn uptime(&self) -> u32 { Simulator::uptime(self) }
     fn finalize(&mut self, time: u64) { Simulator::finalize(self, time) }
     fn overstack(&self) -> u32 { Simulator::overstack(self) }
     fn stripped(&self) -> u32 { Simulator::stripped(self) }
+    fn active(&self) -> u64 { Simulator::active(self) }
+    fn stack_time(&self) -> u64 { Simulator::stack_time(self) }
+    fn generated(&self) -> Option<&FnvHashMap<AgentId, u64>> { Some(Simulator::generated(self)) }
 }
 
-impl<E:Buff> BoxedSimulator<E> for () {
-    fn update(&mut self, _: u64) {}
-    fn add_event(&mut self, _: E) {}
-    fn stacks(&self) -> usize { 0 }
-    fn sum(&self) -> u32 { 0 }
-    fn uptime(&self) -> u32 { 0 }
-    fn finalize(&mut self, _time: u64) {}
-    fn overstack(&self) -> u32 { 0 }
-    fn stripped(&self) -> u32 { 0 }
+/// A buff which was already active when the log started.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InitialBuff {
+    /// Time of the `BuffInitial` state change, milliseconds.
+    pub time:     u64,
+    /// The agent with the buff.
+    pub agent:    AgentId,
+    /// The agent which applied the buff, empty if unknown.
+    pub source:   AgentId,
+    pub skill:    u16,
+    /// Remaining duration at `time`, milliseconds.
+    pub duration: u32,
+}
+
+/// Collects the buffs which were already active when the log started, from the `BuffInitial`
+/// state changes.
+pub fn initial_buffs<E: Event, I: IntoIterator<Item=E>>(events: I) -> Vec<InitialBuff> {
+    events.into_iter()
+          .filter_map(|e| e.into_source())
+          .filter_map(|e| match e.state_change() {
+              Some(StateChange::BuffInitial { skill, duration, target, source }) => Some(InitialBuff {
+                  time:  e.time(),
+                  agent: target,
+                  source,
+                  skill,
+                  duration,
+              }),
+              _ => None,
+          })
+          .collect()
 }
 
 type BoxSimulator<E> = Box<BoxedSimulator<E> + Send>;
@@ -354,27 +820,69 @@ pub enum BuffType {
     Item,
 }
 
+/// How reports group a buff.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Debug)]
+pub enum BuffCategory {
+    OffensiveBoon,
+    DefensiveBoon,
+    Condition,
+    /// Non-boon buffs shared with allies which increase damage, eg. banners.
+    Offensive,
+    /// Non-boon buffs shared with allies which mitigate damage.
+    Defensive,
+    /// Buffs only affecting the agent providing them, eg. traits and sigils.
+    Personal,
+    /// Debuffs applied by bosses as a part of their mechanics.
+    BossDebuff,
+}
+
+/// Which side a buff is applied to, relative to the agent providing it.
+#[derive(Copy, Clone, Eq, PartialEq, Serialize, Debug)]
+pub enum BuffSide {
+    Friendly,
+    Hostile,
+}
+
 #[derive(Copy, Clone, Eq, PartialEq, Serialize, Debug)]
 #[serde(tag = "type")]
 pub enum StackMeta {
     Intensity {
         max: u16
     },
-    Duration,
+    Duration {
+        max: u16
+    },
+    /// Not in the buff table, `max` is the most stacks seen at once.
+    Observed {
+        max: u16
+    },
 }
 
 #[derive(Debug, Clone, Copy, Serialize)]
 pub struct BuffMeta {
     pub name:       &'static str,
-    pub stack: StackMeta,
+    pub stack:      StackMeta,
     #[serde(rename="skillId")]
     pub skill_id:   u16,
-    // TODO: Friendly/hostile, offensive/defensive and so on
+    #[serde(rename="type")]
+    pub kind:       BuffType,
+    pub category:   BuffCategory,
+    pub side:       BuffSide,
+    /// The profession or specialization providing the buff, `None` if it is available to
+    /// everyone.
+    pub profession: Option<Profession>,
 }
 
 #[derive(Debug, Clone, Copy)]
 pub struct MetadataMap(&'static [BuffMeta]);
 
+impl MetadataMap {
+    /// The metadata of the buff, `None` if it is not in the table.
+    pub fn get(&self, skill_id: u16) -> Option<&'static BuffMeta> {
+        self.0.iter().find(|m| m.skill_id == skill_id)
+    }
+}
+
 impl Serialize for MetadataMap {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
         where S: Serializer {
@@ -397,15 +905,51 @@ pub struct BuffSnapshot {
     stripped:  u32,
 }
 
+/// Uptime and time-weighted average stacks of a buff over a time-span.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize)]
+pub struct BuffUptime {
+    /// Time with at least one active stack, milliseconds
+    active:  u64,
+    /// Fraction of the time-span with at least one active stack
+    uptime:  f64,
+    /// Time-weighted average number of stacks
+    #[serde(rename="averageStacks")]
+    average: f64,
+}
+
+impl BuffUptime {
+    /// Uptime over a time-span of `duration` milliseconds.
+    pub fn new(active: u64, stack_time: u64, duration: u64) -> Self {
+        BuffUptime {
+            active,
+            uptime:  if duration == 0 { 0.0 } else { active as f64 / duration as f64 },
+            average: if duration == 0 { 0.0 } else { stack_time as f64 / duration as f64 },
+        }
+    }
+
+    #[inline]
+    pub fn uptime(&self) -> f64 {
+        self.uptime
+    }
+
+    #[inline]
+    pub fn average_stacks(&self) -> f64 {
+        self.average
+    }
+}
+
 #[macro_export]
 macro_rules! buff_table {
     (
         $visibility:tt $module:ident {
         $(
-            $type:ty {
+            $type:path {
                 $name:expr,
                 skill_id: $id:expr,
-                stack: $kind:ty
+                stack: $kind:ty,
+                category: $category:path,
+                side: $side:path,
+                profession: $profession:expr
             }
         ),+
         $(,)*
@@ -422,12 +966,18 @@ $visibility mod $module {
     use serde::ser::SerializeMap;
 
     use $crate::AgentId;
+    use $crate::Profession;
     use $crate::event::Buff;
     use $crate::buff::Simulator;
     use $crate::buff::BoxSimulator;
     use $crate::buff::BuffSnapshot;
+    use $crate::buff::BuffUptime;
+    use $crate::buff::InitialBuff;
     use $crate::buff::MetadataMap;
+    use $crate::buff::BuffCategory;
     use $crate::buff::BuffMeta;
+    use $crate::buff::BuffSide;
+    use $crate::buff::Observed;
     use $crate::buff::Stack;
     use $crate::buff::StackMeta;
 
@@ -440,22 +990,31 @@ $visibility mod $module {
     pub static META_LIST: &'static [BuffMeta] = &[
         $(
         BuffMeta {
-            name:     $name,
-            stack:    stack_meta::<$kind>(),
-            skill_id: $id,
+            name:       $name,
+            stack:      stack_meta::<$kind>(),
+            skill_id:   $id,
+            kind:       $type,
+            category:   $category,
+            side:       $side,
+            profession: $profession,
         }
         ),*
     ];
 
     pub static META_MAP: MetadataMap = MetadataMap(&META_LIST);
 
+    /// The buffs in a category, eg. all the offensive boons.
+    pub fn in_category(category: BuffCategory) -> impl Iterator<Item=&'static BuffMeta> {
+        META_LIST.iter().filter(move |m| m.category == category)
+    }
+
     pub fn create_simulator<E: Buff>(agent_id: AgentId, skill_id: u16) -> BoxSimulator<E> {
         match skill_id {
             $(
             $id => Box::new(Simulator::<$kind>::new(agent_id, Default::default())),
             )*
-            // FIXME: Need to skip the boon
-            _ => Box::new(()),
+            // Infer the stacking from the applications
+            _ => Box::new(Simulator::<Observed>::new(agent_id, Default::default())),
         }
     }
 
@@ -481,12 +1040,40 @@ $visibility mod $module {
 
         pub fn add_event(&mut self, e: E) {
             let agent_id = self.agent_id;
+            let time     = e.time();
 
             self.map.entry(e.skill())
-                    .or_insert_with(|| create_simulator(agent_id, e.skill()))
+                    .or_insert_with(|| {
+                        let mut sim = create_simulator(agent_id, e.skill());
+
+                        // Start the clock at the first event, otherwise the next update would
+                        // count the time since the start of the log
+                        sim.update(time);
+
+                        sim
+                    })
                     .add_event(e);
         }
 
+        /// Seeds the simulators with the buffs of this agent which were already active when the
+        /// log started, with their remaining duration at `start`.
+        pub fn seed(&mut self, initial: &[InitialBuff], start: u64) {
+            let agent_id = self.agent_id;
+
+            for b in initial.iter().filter(|b| b.agent == agent_id) {
+                let duration = b.duration.saturating_sub(start.saturating_sub(b.time) as u32);
+
+                if duration == 0 {
+                    continue;
+                }
+
+                let sim = self.map.entry(b.skill).or_insert_with(|| create_simulator(agent_id, b.skill));
+
+                sim.update(start);
+                sim.add_initial(duration, b.source);
+            }
+        }
+
         pub fn snapshots<'a>(&'a self) -> impl Iterator<Item=(u16, BuffSnapshot)> + 'a {
             self.map.iter().filter(|(_, v)| v.uptime() > 0).map(|(&k, v)| (k, BuffSnapshot {
                 stacks:    v.stacks(),
@@ -498,6 +1085,34 @@ $visibility mod $module {
             }))
         }
 
+        /// Uptime and average stacks of each buff over `duration` milliseconds, should be
+        /// called after `finalize`.
+        pub fn uptimes<'a>(&'a self, duration: u64) -> impl Iterator<Item=(u16, BuffUptime)> + 'a {
+            self.map.iter().filter(|(_, v)| v.active() > 0).map(move |(&k, v)| (k, BuffUptime::new(v.active(), v.stack_time(), duration)))
+        }
+
+        /// Time with at least one active stack and the sum of the time each stack was active per
+        /// buff so far, milliseconds.
+        pub fn totals<'a>(&'a self) -> impl Iterator<Item=(u16, u64, u64)> + 'a {
+            self.map.iter().map(|(&k, v)| (k, v.active(), v.stack_time()))
+        }
+
+        /// Duration which took effect per buff and source agent, milliseconds, see
+        /// `Simulator::generated`.
+        pub fn generated<'a>(&'a self) -> impl Iterator<Item=(u16, AgentId, u64)> + 'a {
+            self.map.iter()
+                    .filter_map(|(&k, v)| v.generated().map(|g| (k, g)))
+                    .flat_map(|(k, g)| g.iter().map(move |(&a, &d)| (k, a, d)))
+        }
+
+        /// Stack metadata of each buff which is not in the table, inferred from the applications.
+        pub fn observed<'a>(&'a self) -> impl Iterator<Item=(u16, StackMeta)> + 'a {
+            self.map.iter().filter_map(|(&k, v)| match v.stack_meta() {
+                m @ StackMeta::Observed { .. } => Some((k, m)),
+                _                              => None,
+            })
+        }
+
         #[inline]
         pub fn len(&self) -> usize {
             self.map.len()
@@ -538,25 +1153,227 @@ $visibility mod $module {
     }
 }
 
-buff_table!(
-pub table {
-    BuffType::Boon{"Aegis",        skill_id: 743,   stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Alacrity",     skill_id: 30328, stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Fury",         skill_id: 725,   stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Might",        skill_id: 740,   stack: Intensity<Replace, [u32; 25]>},
-    BuffType::Boon{"Protection",   skill_id: 717,   stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Quickness",    skill_id: 1187,  stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Regeneration", skill_id: 718,   stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Resistance",   skill_id: 26980, stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Retaliation",  skill_id: 873,   stack: Duration<Queue, [u32; 5]>},
-    BuffType::Boon{"Stability",    skill_id: 1122,  stack: Intensity<Queue, [u32; 25]>},
-    BuffType::Boon{"Swiftness",    skill_id: 719,   stack: Duration<Queue,  [u32; 5]>},
-    BuffType::Boon{"Vigor",        skill_id: 726,   stack: Duration<Queue,  [u32; 5]>},
-    // Conditions
-    // Buffs
-    BuffKind::Buff{"Banner of Strength",   skill_id: 14417, stack: Duration<Replace, [u32; 1]>},
-    BuffKind::Buff{"Banner of Discipline", skill_id: 14449, stack: Duration<Replace, [u32; 1]>},
-});
+// Generated by build.rs from data/buffs.csv
+include!(concat!(env!("OUT_DIR"), "/buff_table.rs"));
+
+/// Buff generation of a single agent per buff, the average duration generated per target
+/// relative to the time-span.
+///
+/// This is the generated uptime for duration-stacking buffs and the generated average stacks for
+/// intensity-stacking buffs.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Generation {
+    /// Generated on the agent itself
+    #[serde(rename="self")]
+    own:       FnvHashMap<u16, f64>,
+    /// Generated on the other members of the subgroup
+    group:     FnvHashMap<u16, f64>,
+    /// Generated on the members of the other subgroups
+    #[serde(rename="offGroup")]
+    off_group: FnvHashMap<u16, f64>,
+    /// Generated on all the other members of the squad
+    squad:     FnvHashMap<u16, f64>,
+}
+
+impl Generation {
+    /// Calculates the generation of `agent` over `duration` milliseconds, `sources` are the agent
+    /// and its gadgets/minions and `targets` are the members of the squad with their buffs.
+    pub fn new<E: Buff>(agent: &Agent, sources: &[AgentId], targets: &[(&Agent, &table::Map<E>)], duration: u64) -> Self {
+        let mut own       = Total::default();
+        let mut group     = Total::default();
+        let mut off_group = Total::default();
+        let mut squad     = Total::default();
+
+        for &(target, buffs) in targets {
+            let totals: Vec<&mut Total> = if target == agent {
+                vec![&mut own]
+            }
+            else if target.subgroup() == agent.subgroup() {
+                vec![&mut group, &mut squad]
+            }
+            else {
+                vec![&mut off_group, &mut squad]
+            };
+
+            for t in totals {
+                t.targets += 1;
+
+                for (skill, _, d) in buffs.generated().filter(|(_, s, _)| sources.contains(s)) {
+                    *t.generated.entry(skill).or_insert(0) += d;
+                }
+            }
+        }
+
+        Generation {
+            own:       own.average(duration),
+            group:     group.average(duration),
+            off_group: off_group.average(duration),
+            squad:     squad.average(duration),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Total {
+    targets:   u64,
+    generated: FnvHashMap<u16, u64>,
+}
+
+impl Total {
+    fn average(&self, duration: u64) -> FnvHashMap<u16, f64> {
+        let span = (duration * self.targets) as f64;
+
+        self.generated.iter().filter(|_| span > 0.0).map(|(&k, &d)| (k, d as f64 / span)).collect()
+    }
+}
+
+/// Uptime and time-averaged stacks per buff in fixed-size time buckets, stored as one column per
+/// buff.
+///
+/// The buffs have to be updated through the series so that the buckets are closed at the exact
+/// bucket boundaries. Time before the start of the series is not part of any bucket.
+#[derive(Debug, Clone, Serialize)]
+pub struct BuffSeries {
+    /// Start of the first bucket, milliseconds.
+    start:   u64,
+    /// Length of each bucket, milliseconds.
+    bucket:  u64,
+    /// Number of closed buckets.
+    len:     usize,
+    buffs:   FnvHashMap<u16, BuffColumns>,
+    /// Active and stack time per buff at the start of the current bucket.
+    #[serde(skip)]
+    last:    FnvHashMap<u16, (u64, u64)>,
+    /// If the buffs have been updated to the start of the series.
+    #[serde(skip)]
+    started: bool,
+}
+
+/// Values of a single buff in a [BuffSeries], one entry per bucket.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BuffColumns {
+    /// Fraction of the bucket with at least one active stack
+    uptime: Vec<f64>,
+    /// Time-weighted average number of stacks
+    #[serde(rename="averageStacks")]
+    stacks: Vec<f64>,
+}
+
+impl BuffSeries {
+    /// Creates a series with buckets of `bucket` milliseconds starting at `start`.
+    pub fn new(start: u64, bucket: u64) -> Self {
+        assert!(bucket > 0, "BuffSeries bucket length must be nonzero");
+
+        BuffSeries {
+            start,
+            bucket,
+            len:     0,
+            buffs:   FnvHashMap::default(),
+            last:    FnvHashMap::default(),
+            started: false,
+        }
+    }
+
+    /// Updates `buffs` to `time`, closing any buckets which ended in between.
+    pub fn update<E: Buff>(&mut self, buffs: &mut table::Map<E>, time: u64) {
+        if ! self.started {
+            buffs.update(cmp::min(time, self.start));
+
+            // Exclude the time before the start from the first bucket
+            for (skill, active, stacked) in buffs.totals() {
+                self.last.insert(skill, (active, stacked));
+            }
+
+            if time < self.start {
+                return;
+            }
+
+            self.started = true;
+        }
+
+        loop {
+            let end = self.start + (self.len as u64 + 1) * self.bucket;
+
+            if end > time {
+                break;
+            }
+
+            buffs.update(end);
+
+            self.close(buffs, self.bucket);
+        }
+
+        buffs.update(time);
+    }
+
+    /// Updates and finalizes `buffs` at `time` and closes the last, possibly partial, bucket.
+    pub fn finalize<E: Buff>(&mut self, buffs: &mut table::Map<E>, time: u64) {
+        self.update(buffs, time);
+
+        buffs.finalize(time);
+
+        let start = self.start + self.len as u64 * self.bucket;
+
+        if time > start {
+            self.close(buffs, time - start);
+        }
+    }
+
+    /// Number of buckets.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// The columns of a buff, `None` if the buff has never been active.
+    #[inline]
+    pub fn get(&self, skill: u16) -> Option<&BuffColumns> {
+        self.buffs.get(&skill)
+    }
+
+    fn close<E: Buff>(&mut self, buffs: &table::Map<E>, length: u64) {
+        let len = self.len;
+
+        for (skill, active, stacked) in buffs.totals() {
+            let last = self.last.entry(skill).or_default();
+            let (a, s) = (active - last.0, stacked - last.1);
+
+            *last = (active, stacked);
+
+            // Skip buffs which have not been active so far
+            if a == 0 && ! self.buffs.contains_key(&skill) {
+                continue;
+            }
+
+            let col = self.buffs.entry(skill).or_insert_with(|| BuffColumns {
+                uptime: vec![0.0; len],
+                stacks: vec![0.0; len],
+            });
+
+            col.uptime.push(a as f64 / length as f64);
+            col.stacks.push(s as f64 / length as f64);
+        }
+
+        self.len += 1;
+    }
+}
+
+impl BuffColumns {
+    #[inline]
+    pub fn uptime(&self) -> &[f64] {
+        &self.uptime
+    }
+
+    #[inline]
+    pub fn average_stacks(&self) -> &[f64] {
+        &self.stacks
+    }
+}
 
 #[cfg(test)]
 mod test {
@@ -564,15 +1381,16 @@ mod test {
 
     #[test]
     fn stack1() {
-        let mut a = Duration(Queue, [3, 2, 0, 0, 0]);
+        let mut a = Duration(Queue, [3, 2, 0, 0, 0], [1, 2, 0, 0, 0]);
 
         assert_eq!(a.stacks(), 2);
         assert_eq!(a.sum(), 5);
 
-        a.push(4);
+        a.push(4, 3);
 
         // The currently active stack is not modified
         assert_eq!(a.1, [3, 4, 2, 0, 0]);
+        assert_eq!(a.2, [1, 3, 2, 0, 0]);
         assert_eq!(a.stacks(), 3);
         assert_eq!(a.sum(), 9);
 
@@ -585,8 +1403,519 @@ mod test {
 
         a.update(4);
 
+        // The sources are kept with the stacks when sorted
         assert_eq!(a.1, [1, 2, 0, 0, 0]);
+        assert_eq!(a.remaining(), [(1, 3), (2, 2)]);
         assert_eq!(a.stacks(), 2);
         assert_eq!(a.sum(), 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn elapsed() {
+        let mut a = Intensity(Replace, [0; 25], [0; 25]);
+
+        a.push(3, 0);
+        a.push(5, 0);
+        a.push(1, 0);
+
+        assert_eq!(a.update(2), Elapsed { active: 2, stacks: 5 });
+        assert_eq!(a.stacks(), 2);
+        assert_eq!(a.update(4), Elapsed { active: 3, stacks: 4 });
+        assert_eq!(a.stacks(), 0);
+
+        let mut d = Duration(Queue, [3, 2, 0, 0, 0], [0; 5]);
+
+        assert_eq!(d.update(4), Elapsed { active: 4, stacks: 4 });
+        assert_eq!(d.update(4), Elapsed { active: 1, stacks: 1 });
+    }
+
+    #[test]
+    fn remove_single() {
+        let mut d = Duration(Queue, [3, 7, 2, 0, 0], [1, 2, 3, 0, 0]);
+
+        assert_eq!(d.remove(6), (7, 2));
+        assert_eq!(d.remaining(), [(3, 1), (2, 3)]);
+        assert_eq!(d.remove(0), (2, 3));
+        assert_eq!(d.1, [3, 0, 0, 0, 0]);
+
+        let mut a = Intensity(Replace, [0; 25], [0; 25]);
+
+        a.push(3, 1);
+        a.push(5, 2);
+
+        assert_eq!(a.remove(5), (5, 2));
+        assert_eq!(a.stacks(), 1);
+        assert_eq!(a.sum(), 3);
+    }
+
+    #[test]
+    fn expiry_matches_intensity() {
+        let mut a = Intensity(Replace, [0; 25], [0; 25]);
+        let mut h = Expiry::<Replace, [u32; 25]>::default();
+        // Simple LCG to get a reproducible mix of operations
+        let mut seed = 12345u32;
+        let mut next = |m: u32| {
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+
+            (seed >> 8) % m
+        };
+
+        let mut ties = 0;
+
+        for _ in 0..10000 {
+            let op = next(10);
+
+            if op < 6 {
+                let d = next(8000) + 1;
+                let s = next(10);
+
+                // Stacks of the same duration might be from different sources
+                assert_eq!(a.push(d, s).0, h.push(d, s).0);
+            }
+            else if op < 9 {
+                let t = next(1500);
+
+                assert_eq!(a.update(t), h.update(t));
+            }
+            else {
+                let mut active: Vec<u32> = a.1.iter().cloned().filter(|&s| s > 0).collect();
+
+                active.sort();
+
+                // Remove between two close stacks every other time to exercise the tie-break,
+                // otherwise close to a random stack
+                let pair = active.windows(2).find(|w| (w[1] - w[0]) % 2 == 0 && w[1] - w[0] <= 2 * REMOVAL_TOLERANCE);
+                let d    = match pair {
+                    Some(w) if next(2) == 0 => { ties += 1; w[0] + (w[1] - w[0]) / 2 },
+                    _ if active.is_empty()  => next(8000),
+                    _                       => (active[next(active.len() as u32) as usize] + next(4 * REMOVAL_TOLERANCE)).saturating_sub(2 * REMOVAL_TOLERANCE),
+                };
+
+                assert_eq!(a.remove(d).0, h.remove(d).0);
+            }
+
+            assert_eq!(a.stacks(), h.stacks());
+            assert_eq!(a.sum(), h.sum());
+        }
+
+        assert!(ties > 0);
+
+        let mut a = Intensity(Replace, [0; 25], [0; 25]);
+        let mut h = Expiry::<Replace, [u32; 25]>::default();
+
+        for &(d, s) in &[(3040, 1), (2960, 2), (5000, 3)] {
+            a.push(d, s);
+            h.push(d, s);
+        }
+
+        // 2960 and 3040 are equally close
+        assert_eq!(a.remove(3000), (3040, 1));
+        assert_eq!(h.remove(3000), (3040, 1));
+        // Nothing is close enough
+        assert_eq!(a.remove(4000), (0, 0));
+        assert_eq!(h.remove(4000), (0, 0));
+    }
+
+    #[test]
+    fn categories() {
+        let mut offensive: Vec<_> = table::in_category(BuffCategory::OffensiveBoon).map(|m| m.skill_id).collect();
+
+        offensive.sort();
+
+        // Fury, Might, Quickness, Alacrity
+        assert_eq!(offensive, [725, 740, 1187, 30328]);
+        assert!(table::in_category(BuffCategory::Condition).all(|m| m.kind == BuffType::Condition && m.side == BuffSide::Hostile));
+
+        let mut boss: Vec<_> = table::in_category(BuffCategory::BossDebuff).map(|m| m.skill_id).collect();
+
+        boss.sort();
+
+        // Unbalanced, Volatile Poison, Fixated, Shared Agony, Necrosis
+        assert_eq!(boss, [34367, 34387, 37868, 38049, 47414]);
+        assert!(table::in_category(BuffCategory::BossDebuff).all(|m| m.kind == BuffType::Debuff && m.side == BuffSide::Hostile));
+        assert!(table::in_category(BuffCategory::Defensive).any(|m| m.skill_id == 5587));
+        assert!(table::in_category(BuffCategory::Personal).all(|m| m.side == BuffSide::Friendly));
+
+        let shroud = table::META_MAP.get(29446).unwrap();
+
+        assert_eq!(shroud.category, BuffCategory::Personal);
+        assert_eq!(shroud.profession, Some(Profession::Reaper));
+        assert_eq!(table::META_MAP.get(9286).unwrap().profession, None);
+
+        let might = ::serde_json::to_value(table::META_MAP.get(740).unwrap()).unwrap();
+
+        assert_eq!(might["skillId"], 740);
+        assert_eq!(might["type"], "Boon");
+        assert_eq!(might["category"], "OffensiveBoon");
+        assert_eq!(might["side"], "Friendly");
+        assert_eq!(might["stack"]["type"], "Intensity");
+        assert_eq!(might["stack"]["max"], 25);
+
+        let json = ::serde_json::to_value(&table::META_MAP).unwrap();
+
+        assert_eq!(json["736"]["category"], "Condition");
+        assert_eq!(json["736"]["side"], "Hostile");
+    }
+
+    #[test]
+    fn observed() {
+        let mut o = Observed::default();
+
+        o.push(3, 0);
+        o.push(5, 1);
+        o.push(1, 2);
+
+        assert_eq!(o.update(2), Elapsed { active: 2, stacks: 5 });
+        assert_eq!(o.stacks(), 2);
+        assert_eq!(o.sum(), 4);
+        assert_eq!(o.remove(3), (3, 1));
+        assert_eq!(o.remove(1000), (0, 0));
+        assert_eq!(o.stacks(), 1);
+        assert_eq!(o.max_stacks(), 3);
+        assert_eq!(o.stack_meta(), StackMeta::Observed { max: 3 });
+    }
+
+    #[test]
+    fn buff_series() {
+        use event::BuffEvent;
+        use event::raw::CombatEventV1;
+
+        let agent   = AgentId::new(1);
+        let initial = [
+            InitialBuff { time: 0, agent, source: agent, skill: 740, duration: 1500 },
+            InitialBuff { time: 0, agent, source: agent, skill: 740, duration: 500 },
+        ];
+        let mut m: table::Map<BuffEvent<&'static CombatEventV1>> = table::Map::new(agent);
+        let mut s = BuffSeries::new(0, 1000);
+
+        m.seed(&initial, 0);
+        s.update(&mut m, 1200);
+        s.finalize(&mut m, 2500);
+
+        let might = s.get(740).unwrap();
+
+        assert_eq!(s.len(), 3);
+        assert_eq!(might.uptime(), &[1.0, 0.5, 0.0]);
+        assert_eq!(might.average_stacks(), &[1.5, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn buff_series_applied() {
+        use event::Event;
+        use event::raw::CombatEventV1;
+
+        let events = [
+            CombatEventV1::new(10500).src(2, 0).dst(1, 0).buff_apply(1187, 5000),
+            CombatEventV1::new(12000).src(2, 0).dst(1, 0).buff_apply(1187, 1000),
+        ];
+        let mut m = table::Map::new(AgentId::new(1));
+        let mut s = BuffSeries::new(0, 1000);
+
+        for e in events.iter().filter_map(Event::into_buff) {
+            s.update(&mut m, e.time());
+            m.add_event(e);
+        }
+
+        s.finalize(&mut m, 20000);
+
+        let quickness = s.get(1187).unwrap();
+
+        assert_eq!(s.len(), 20);
+        assert_eq!(&quickness.uptime()[9..18], &[0.0, 0.5, 1.0, 1.0, 1.0, 1.0, 1.0, 0.5, 0.0]);
+        assert_eq!(quickness.uptime().iter().sum::<f64>(), 6.0);
+        assert!(quickness.uptime().iter().all(|&u| u <= 1.0));
+    }
+
+    #[test]
+    fn buff_series_before_start() {
+        use event::Event;
+        use event::raw::CombatEventV1;
+
+        // Quickness from 2s to 7s and Might from 1s to 4s, the series starts at 5s
+        let events = [
+            CombatEventV1::new(1000).src(2, 0).dst(1, 0).buff_apply(740, 3000),
+            CombatEventV1::new(2000).src(2, 0).dst(1, 0).buff_apply(1187, 5000),
+        ];
+        let mut m = table::Map::new(AgentId::new(1));
+        let mut s = BuffSeries::new(5000, 1000);
+
+        for e in events.iter().filter_map(Event::into_buff) {
+            s.update(&mut m, e.time());
+            m.add_event(e);
+        }
+
+        s.finalize(&mut m, 10000);
+
+        let quickness = s.get(1187).unwrap();
+
+        assert_eq!(s.len(), 5);
+        assert_eq!(quickness.uptime(), &[1.0, 1.0, 0.0, 0.0, 0.0]);
+        assert_eq!(quickness.average_stacks(), &[1.0, 1.0, 0.0, 0.0, 0.0]);
+        // Never active after the start
+        assert!(s.get(740).is_none());
+    }
+
+    #[test]
+    fn boss_condition_uptime() {
+        use event::Event;
+        use event::raw::CombatEventV1;
+
+        // Bleeding on the boss from 10s to 15s and 12s to 17s
+        let events = [
+            CombatEventV1::new(10000).src(2, 0).dst(1, 0).buff_apply(736, 5000),
+            CombatEventV1::new(12000).src(2, 0).dst(1, 0).buff_apply(736, 5000),
+        ];
+        let mut m = table::Map::new(AgentId::new(1));
+
+        for e in events.iter().filter_map(Event::into_buff) {
+            m.update(e.time());
+            m.add_event(e);
+        }
+
+        m.update(20000);
+        m.finalize(20000);
+
+        let uptimes: Vec<_> = m.uptimes(20000).collect();
+
+        assert_eq!(uptimes.len(), 1);
+        assert_eq!(uptimes[0].0, 736);
+        assert_eq!(uptimes[0].1.uptime(), 0.35);
+        assert_eq!(uptimes[0].1.average_stacks(), 0.5);
+    }
+
+    #[test]
+    fn seed_initial() {
+        use event::BuffEvent;
+        use event::raw::CombatEventV1;
+
+        let agent   = AgentId::new(1);
+        let other   = AgentId::new(2);
+        let initial = [
+            InitialBuff { time: 0, agent, source: other, skill: 743, duration: 5000 },
+            InitialBuff { time: 0, agent, source: other, skill: 740, duration: 500 },
+            InitialBuff { time: 0, agent: other, source: other, skill: 743, duration: 5000 },
+            // Does not run out
+            InitialBuff { time: 0, agent, source: AgentId::empty(), skill: 1187, duration: i32::MAX as u32 },
+        ];
+        let mut m: table::Map<BuffEvent<&'static CombatEventV1>> = table::Map::new(agent);
+
+        // Might has already expired when the encounter starts
+        m.seed(&initial, 1000);
+        m.finalize(3000);
+
+        let mut uptimes: Vec<_> = m.uptimes(2000).map(|(k, u)| (k, u.uptime())).collect();
+
+        uptimes.sort_by_key(|&(k, _)| k);
+
+        assert_eq!(uptimes, [(743, 1.0), (1187, 1.0)]);
+        // Only the part during the encounter counts, nothing for the unknown source
+        assert_eq!(m.generated().collect::<Vec<_>>(), [(743, other, 2000)]);
+    }
+
+    #[test]
+    fn decode_initial() {
+        use event::raw::CombatEventV1;
+        use event::raw::CombatStateChange;
+
+        let initial = |src, dst, value| CombatEventV1::new(1000).src(src, 0).dst(dst, 0).skill(743).value(value).state_change(CombatStateChange::BuffInitial);
+        let events  = [initial(1, 2, 5000), initial(1, 0, 3000), initial(1, 2, -1), initial(1, 2, i32::MAX)];
+        let forever = i32::MAX as u32;
+
+        assert_eq!(initial_buffs(events.iter()), vec![
+            InitialBuff { time: 1000, agent: AgentId::new(2), source: AgentId::new(1), skill: 743, duration: 5000 },
+            // Falls back to the source without a target
+            InitialBuff { time: 1000, agent: AgentId::new(1), source: AgentId::empty(), skill: 743, duration: 3000 },
+            // Negative durations do not run out either
+            InitialBuff { time: 1000, agent: AgentId::new(2), source: AgentId::new(1), skill: 743, duration: forever },
+            InitialBuff { time: 1000, agent: AgentId::new(2), source: AgentId::new(1), skill: 743, duration: forever },
+        ]);
+    }
+
+    #[test]
+    fn generation_applied() {
+        use event::Event;
+        use event::raw;
+        use event::raw::CombatEventV1;
+        use event::raw::CombatStateChange;
+        use synthetic::Log;
+        use Metadata;
+
+        let mut log = Log::new(15438);
+
+        log.npc(1, 15438, "Boss")
+           .player(2, 1, "A", 1)
+           .player(3, 2, "B", 1)
+           .player(4, 3, "C", 2)
+           .event(CombatEventV1::new(1000).src(1, 1).state_change(CombatStateChange::EnterCombat))
+           // Quickness on the subgroup and Might on B and C, first applied mid-fight
+           .event(CombatEventV1::new(10000).src(2, 2).dst(2, 2).buff_apply(1187, 5000))
+           .event(CombatEventV1::new(10000).src(2, 2).dst(3, 3).buff_apply(1187, 5000))
+           .event(CombatEventV1::new(12000).src(2, 2).dst(4, 4).buff_apply(740, 4000))
+           .event(CombatEventV1::new(12000).src(2, 2).dst(3, 3).buff_apply(740, 4000))
+           .event(CombatEventV1::new(21000).src(1, 1).state_change(CombatStateChange::ChangeDead));
+
+        let buffer = log.to_bytes();
+        let evtc   = raw::transmute(&buffer);
+        let meta   = Metadata::new(&evtc);
+
+        let players: Vec<_> = meta.agents().iter().filter(|a| a.profession().is_player_character()).collect();
+        let mut maps: Vec<_> = players.iter().map(|a| table::Map::new(a.id())).collect();
+
+        for e in meta.encounter_events().filter_map(Event::into_buff) {
+            for m in &mut maps {
+                m.update(e.time());
+                m.add_event(e.clone());
+            }
+        }
+
+        for m in &mut maps {
+            m.finalize(meta.encounter().end());
+        }
+
+        assert_eq!(meta.encounter().duration_ms(), 20000);
+
+        let targets: Vec<_> = players.iter().cloned().zip(maps.iter()).collect();
+        let g = Generation::new(players[0], &[players[0].id()], &targets, 20000);
+
+        assert_eq!(g.own.get(&1187), Some(&0.25));
+        assert_eq!(g.group.get(&1187), Some(&0.25));
+        assert_eq!(g.off_group.get(&1187), None);
+        // Average stacks over both other players
+        assert_eq!(g.squad.get(&740), Some(&0.2));
+        assert_eq!(g.off_group.get(&740), Some(&0.2));
+
+        // Uptime on the target only counts from the application
+        let quickness: Vec<_> = maps[1].uptimes(20000).filter(|&(k, _)| k == 1187).collect();
+
+        assert_eq!(quickness[0].1.uptime(), 0.25);
+    }
+
+    #[test]
+    fn simulate_remove_single() {
+        use event::Event;
+        use event::raw::CombatBuffRemove;
+        use event::raw::CombatEventV1;
+
+        let agent = AgentId::new(1);
+        let apply = |d| CombatEventV1::new(0).src(2, 0).dst(1, 0).buff_apply(1187, d);
+        let strip = |d, r| CombatEventV1::new(0).src(1, 0).dst(3, 0).buff_remove(1187, d, r);
+
+        // Duration: the closest stack is removed even if it is queued
+        let events = [apply(3000), apply(7000), apply(2000), strip(6980, CombatBuffRemove::Single), strip(2000, CombatBuffRemove::Manual)];
+        let mut s  = Simulator::new(agent, Duration(Queue, [0; 5], [0; 5]));
+
+        for e in events.iter().take(4).filter_map(Event::into_buff) {
+            s.add_event(e);
+        }
+
+        assert_eq!(s.stack.1, [3000, 2000, 0, 0, 0]);
+        assert_eq!(s.stripped(), 7000);
+
+        // Manual removals do not count as stripped
+        s.add_event(events[4].into_buff().unwrap());
+
+        assert_eq!(s.stack.1, [3000, 0, 0, 0, 0]);
+        assert_eq!(s.stripped(), 7000);
+
+        // Intensity: the closest stack is removed
+        let events = [apply(3000), apply(5000), apply(1000), strip(5000, CombatBuffRemove::Single), strip(4000, CombatBuffRemove::All)];
+        let mut s  = Simulator::new(agent, Intensity(Replace, [0; 25], [0; 25]));
+
+        for e in events.iter().take(4).filter_map(Event::into_buff) {
+            s.add_event(e);
+        }
+
+        assert_eq!(s.stacks(), 2);
+        assert_eq!(s.sum(), 4000);
+        assert_eq!(s.stripped(), 5000);
+
+        s.add_event(events[4].into_buff().unwrap());
+
+        assert_eq!(s.stacks(), 0);
+        assert_eq!(s.stripped(), 9000);
+    }
+
+    #[test]
+    fn simulate_natural_expiry() {
+        use event::Event;
+        use event::raw::CombatBuffRemove;
+        use event::raw::CombatEventV1;
+
+        let agent  = AgentId::new(1);
+        let apply  = |t, d| CombatEventV1::new(t).src(2, 0).dst(1, 0).buff_apply(740, d);
+        let remove = |t, d, r| CombatEventV1::new(t).src(1, 0).dst(0, 0).buff_remove(740, d, r);
+
+        // The first stack of Might expires while the second one is still active
+        let events = [
+            apply(0, 3000),
+            apply(0, 8000),
+            remove(3000, 0, CombatBuffRemove::Manual),
+            remove(3000, 20, CombatBuffRemove::Single),
+            // The last stack expiring
+            remove(8000, 0, CombatBuffRemove::All),
+        ];
+        let mut s = Simulator::new(agent, Intensity(Replace, [0; 25], [0; 25]));
+
+        for e in events.iter().take(4).filter_map(Event::into_buff) {
+            s.update(e.time());
+            s.add_event(e);
+        }
+
+        assert_eq!(s.stacks(), 1);
+        assert_eq!(s.sum(), 5000);
+        assert_eq!(s.stripped(), 0);
+
+        let e = events[4].into_buff().unwrap();
+
+        s.update(e.time());
+        s.add_event(e);
+        s.finalize(10000);
+
+        assert_eq!(s.stacks(), 0);
+        assert_eq!(s.stripped(), 0);
+        assert_eq!(s.uptime(), 11000);
+        assert_eq!(s.active(), 8000);
+    }
+
+    #[test]
+    fn generation_revoked() {
+        use event::Event;
+        use event::raw::CombatBuffRemove;
+        use event::raw::CombatEventV1;
+
+        let agent  = AgentId::new(1);
+        let (a, b) = (AgentId::new(2), AgentId::new(3));
+        let apply  = |t, src, d| CombatEventV1::new(t).src(src, 0).dst(1, 0).buff_apply(740, d);
+        let strip  = |t, d| CombatEventV1::new(t).src(1, 0).dst(4, 0).buff_remove(740, d, CombatBuffRemove::Single);
+
+        // A's stack is stripped after a second and B's stack is cut off by the end of the log
+        let events = [apply(0, 2, 5000), apply(0, 3, 6000), strip(1000, 4000)];
+        let mut s  = Simulator::new(agent, Intensity(Replace, [0; 25], [0; 25]));
+
+        for e in events.iter().filter_map(Event::into_buff) {
+            s.update(e.time());
+            s.add_event(e);
+        }
+
+        s.finalize(3000);
+
+        assert_eq!(s.stripped(), 4000);
+        assert_eq!(s.generated().get(&a), Some(&1000));
+        assert_eq!(s.generated().get(&b), Some(&3000));
+
+        // B replaces the remainder of A's stack
+        let events = [apply(0, 2, 3000), apply(1000, 3, 5000)];
+        let mut s  = Simulator::new(agent, Intensity(Replace, [0; 1], [0; 1]));
+
+        for e in events.iter().filter_map(Event::into_buff) {
+            s.update(e.time());
+            s.add_event(e);
+        }
+
+        s.finalize(10000);
+
+        assert_eq!(s.overstack(), 2000);
+        assert_eq!(s.generated().get(&a), Some(&1000));
+        assert_eq!(s.generated().get(&b), Some(&5000));
+    }
+}
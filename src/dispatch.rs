@@ -0,0 +1,189 @@
+//! Single-pass routing of events to many consumers at once.
+//!
+//! Instead of filtering the whole event list once per agent of interest, consumers are registered
+//! with a [Dispatcher] which then walks the events once and only hands each event to the
+//! consumers it is relevant for.
+//!
+//! ```ignore
+//! let mut hits: Vec<Hits> = players.iter().map(|_| Hits::default()).collect();
+//!
+//! {
+//!     let mut d = Dispatcher::new();
+//!
+//!     for (a, h) in players.iter().zip(hits.iter_mut()) {
+//!         d.add_agent(a, Route::Source, move |e| if let Some(e) = e.into_damage() { h.add_event(e) });
+//!     }
+//!
+//!     d.run(meta.encounter_events());
+//! }
+//! ```
+//!
+//! When each agent is processed independently the events can instead be collected into one list
+//! per agent using a [Dispatcher] and then processed using [map_partitions], which runs on a
+//! thread-pool if the `parallel` feature is enabled.
+use Agent;
+use AgentId;
+use InstanceId;
+
+use event::Event;
+use event::Source;
+use event::Target;
+
+use fnv::FnvHashMap;
+
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+/// Which events a per-agent consumer receives.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Route {
+    /// Events originating from the agent or any of its gadgets/minions, same as
+    /// [EventIteratorExt::from_agent_or_gadgets](../trait.EventIteratorExt.html).
+    Source,
+    /// Events originating from the agent, its gadgets/minions, or targeting the agent, same as
+    /// [EventIteratorExt::related_to](../trait.EventIteratorExt.html).
+    Related,
+}
+
+/// Walks a list of events once and routes each event to the registered consumers.
+pub struct Dispatcher<'c, E: Event> {
+    /// Consumers receiving every event.
+    global:    Vec<Box<dyn FnMut(E) + 'c>>,
+    /// Consumers receiving events related to a specific agent.
+    consumers: Vec<Box<dyn FnMut(E::SourceEvent) + 'c>>,
+    by_agent:  FnvHashMap<AgentId, Vec<usize>>,
+    by_master: FnvHashMap<InstanceId, Vec<usize>>,
+    by_target: FnvHashMap<AgentId, Vec<usize>>,
+}
+
+impl<'c, E: Event> Default for Dispatcher<'c, E> {
+    fn default() -> Self {
+        Dispatcher {
+            global:    Vec::new(),
+            consumers: Vec::new(),
+            by_agent:  FnvHashMap::default(),
+            by_master: FnvHashMap::default(),
+            by_target: FnvHashMap::default(),
+        }
+    }
+}
+
+impl<'c, E: Event> Dispatcher<'c, E> {
+    #[inline]
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Registers a consumer which receives every event.
+    pub fn add_global<F: FnMut(E) + 'c>(&mut self, f: F) {
+        self.global.push(Box::new(f));
+    }
+
+    /// Registers a consumer which receives the events of the agent, depending on `route`.
+    pub fn add_agent<F: FnMut(E::SourceEvent) + 'c>(&mut self, agent: &Agent, route: Route, f: F) {
+        let index = self.consumers.len();
+
+        self.consumers.push(Box::new(f));
+        self.by_agent.entry(agent.id()).or_default().push(index);
+
+        if agent.instance_id() != InstanceId::empty() {
+            self.by_master.entry(agent.instance_id()).or_default().push(index);
+        }
+
+        if route == Route::Related {
+            self.by_target.entry(agent.id()).or_default().push(index);
+        }
+    }
+
+    /// Also routes the events targeting `gadget` to the consumers of `master` registered with
+    /// `Route::Related`, eg. to see the strips and cleanses done by minions.
+    ///
+    /// Only consumers registered before the call are affected.
+    pub fn add_gadget(&mut self, master: &Agent, gadget: &Agent) {
+        if let Some(v) = self.by_target.get(&master.id()).cloned() {
+            self.by_target.entry(gadget.id()).or_default().extend(v);
+        }
+    }
+
+    /// Routes all the events to the registered consumers, in order.
+    pub fn run<I: IntoIterator<Item=E>>(mut self, events: I) {
+        let mut matched = Vec::new();
+
+        for e in events {
+            for c in self.global.iter_mut() {
+                c(e.clone());
+            }
+
+            if self.consumers.is_empty() {
+                continue;
+            }
+
+            if let Some(e) = e.into_source() {
+                matched.clear();
+
+                if let Some(v) = self.by_agent.get(&e.agent()) {
+                    matched.extend_from_slice(v);
+                }
+
+                if let Some(v) = e.master_instance().and_then(|i| self.by_master.get(&i)) {
+                    matched.extend_from_slice(v);
+                }
+
+                if ! self.by_target.is_empty() {
+                    if let Some(v) = e.clone().into_target().and_then(|t| self.by_target.get(&t.target_agent())) {
+                        matched.extend_from_slice(v);
+                    }
+                }
+
+                // An agent can target itself or its own gadgets
+                matched.sort_unstable();
+                matched.dedup();
+
+                for &i in &matched {
+                    (self.consumers[i])(e.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Maps each list of events using `f`, which also receives the index of the list.
+///
+/// Runs in parallel if the `parallel` feature is enabled, the output order always matches the
+/// input order.
+#[cfg(feature = "parallel")]
+pub fn map_partitions<T, R, F>(parts: Vec<Vec<T>>, f: F) -> Vec<R>
+  where T: Send,
+        R: Send,
+        F: Fn(usize, Vec<T>) -> R + Sync {
+    parts.into_par_iter().enumerate().map(|(i, p)| f(i, p)).collect()
+}
+
+/// Maps each list of events using `f`, which also receives the index of the list.
+///
+/// Runs in parallel if the `parallel` feature is enabled, the output order always matches the
+/// input order.
+#[cfg(not(feature = "parallel"))]
+pub fn map_partitions<T, R, F>(parts: Vec<Vec<T>>, f: F) -> Vec<R>
+  where T: Send,
+        R: Send,
+        F: Fn(usize, Vec<T>) -> R + Sync {
+    parts.into_iter().enumerate().map(|(i, p)| f(i, p)).collect()
+}
+
+/// The metadata and event data have to be shareable between threads for [map_partitions].
+#[allow(dead_code)]
+fn assert_shareable() {
+    use Metadata;
+    use buff::table::Map;
+    use event::BuffEvent;
+    use event::SourceEvent;
+    use event::raw::CombatEventV1;
+
+    fn is_sync<T: Sync>() {}
+    fn is_send<T: Send>() {}
+
+    is_sync::<Metadata>();
+    is_send::<SourceEvent<&CombatEventV1>>();
+    is_send::<Map<BuffEvent<&CombatEventV1>>>();
+}
@@ -0,0 +1,201 @@
+use Agent;
+use Metadata;
+
+use event::Event;
+use event::Source;
+use event::StateChange;
+use event::Target;
+
+use time;
+
+use std::cmp;
+use std::time::Duration;
+
+/// How the start of an [Encounter] is determined.
+///
+/// Defaults to `EnterCombat`, the same as the `--start` default of the CLI.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum EncounterStart {
+    /// The first time any of the bosses were observed.
+    FirstAware,
+    /// The first time any of the bosses entered combat.
+    #[default]
+    EnterCombat,
+    /// The first time any of the bosses were damaged.
+    FirstBossDamage,
+    /// The start of the log.
+    LogStart,
+}
+
+/// How the end of an [Encounter] is determined.
+///
+/// Defaults to `BossDeath`, the same as the `--end` default of the CLI.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Default)]
+pub enum EncounterEnd {
+    /// The last time any of the bosses were observed.
+    LastAware,
+    /// When the last of the bosses died, `LastAware` if any boss survived.
+    #[default]
+    BossDeath,
+    /// The end of the log.
+    LogEnd,
+}
+
+/// The time-span of the fight, all statistics and time-series are limited to this span.
+///
+/// Strategies depending on events fall back to the first/last time the bosses were observed if
+/// there are no matching events.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub struct Encounter {
+    /// Relative milliseconds
+    start:    u64,
+    /// Relative milliseconds
+    end:      u64,
+    /// Milliseconds
+    duration: u64,
+}
+
+impl Encounter {
+    pub fn new(meta: &Metadata, start: EncounterStart, end: EncounterEnd) -> Self {
+        let bosses: Vec<&Agent> = meta.bosses().collect();
+
+        let first_aware = bosses.iter().map(|a| a.first_aware()).min().unwrap_or(meta.log_start());
+        let last_aware  = bosses.iter().map(|a| a.last_aware()).max().unwrap_or(meta.log_end());
+
+        let start = match start {
+            EncounterStart::FirstAware      => first_aware,
+            EncounterStart::LogStart        => meta.log_start(),
+            EncounterStart::EnterCombat     => meta.events().iter()
+                                                   .filter_map(Event::into_source)
+                                                   .find(|e| match e.state_change() {
+                                                       Some(StateChange::EnterCombat(_)) => bosses.iter().any(|b| b.id() == e.agent()),
+                                                       _                                 => false,
+                                                   })
+                                                   .map(|e| e.time())
+                                                   .unwrap_or(first_aware),
+            EncounterStart::FirstBossDamage => meta.events().iter()
+                                                   .filter_map(Event::into_damage)
+                                                   .find(|e| bosses.iter().any(|b| b.id() == e.target_agent()))
+                                                   .map(|e| e.time())
+                                                   .unwrap_or(first_aware),
+        };
+
+        let end = match end {
+            EncounterEnd::LastAware => last_aware,
+            EncounterEnd::LogEnd    => meta.log_end(),
+            EncounterEnd::BossDeath => bosses.iter()
+                                             .map(|a| a.died())
+                                             .collect::<Option<Vec<_>>>()
+                                             .and_then(|d| d.into_iter().max())
+                                             .unwrap_or(last_aware),
+        };
+
+        Encounter::from_span(start, end)
+    }
+
+    /// An encounter spanning the given relative times, `end` is clamped to not precede `start`.
+    pub fn from_span(start: u64, end: u64) -> Self {
+        let end = cmp::max(start, end);
+
+        Encounter {
+            start,
+            end,
+            duration: end - start,
+        }
+    }
+
+    /// Start of the encounter, relative milliseconds
+    #[inline]
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    /// End of the encounter, relative milliseconds
+    #[inline]
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    /// Length of the encounter in milliseconds
+    #[inline]
+    pub fn duration_ms(&self) -> u64 {
+        self.duration
+    }
+
+    #[inline]
+    pub fn duration(&self) -> Duration {
+        time::elapsed(self.start, self.end)
+    }
+
+    /// Time since the start of the encounter, zero if before the start.
+    #[inline]
+    pub fn elapsed(&self, time: u64) -> Duration {
+        time::elapsed(self.start, time)
+    }
+
+    /// True if the relative time is within the encounter, inclusive.
+    #[inline]
+    pub fn contains(&self, time: u64) -> bool {
+        self.start <= time && time <= self.end
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use event::raw;
+    use event::raw::CombatEventV1;
+    use event::raw::CombatStateChange;
+    use event::raw::HitResult;
+    use synthetic::Log;
+
+    fn span(buffer: &[u8], start: EncounterStart, end: EncounterEnd) -> (u64, u64) {
+        let evtc = raw::transmute(buffer);
+        let meta = Metadata::new(&evtc);
+        let e    = Encounter::new(&meta, start, end);
+
+        (e.start(), e.end())
+    }
+
+    #[test]
+    fn strategies() {
+        let mut log = Log::new(15438);
+
+        log.npc(1, 15438, "Boss")
+           .player(2, 1, "A", 1)
+           .event(CombatEventV1::new(500).src(2, 2).state_change(CombatStateChange::EnterCombat))
+           .event(CombatEventV1::new(1000).src(1, 1).state_change(CombatStateChange::MaxHealthUpdate))
+           .event(CombatEventV1::new(2000).src(1, 1).state_change(CombatStateChange::EnterCombat))
+           .event(CombatEventV1::new(3000).src(2, 2).dst(1, 1).skill(100).damage(500, 0, HitResult::Normal))
+           .event(CombatEventV1::new(8000).src(1, 1).state_change(CombatStateChange::ChangeDead))
+           .event(CombatEventV1::new(9000).src(1, 1).state_change(CombatStateChange::ExitCombat))
+           .event(CombatEventV1::new(10000).src(2, 2).state_change(CombatStateChange::ExitCombat));
+
+        let buffer = log.to_bytes();
+
+        assert_eq!(span(&buffer, EncounterStart::FirstAware, EncounterEnd::LastAware), (1000, 9000));
+        assert_eq!(span(&buffer, EncounterStart::EnterCombat, EncounterEnd::BossDeath), (2000, 8000));
+        assert_eq!(span(&buffer, EncounterStart::FirstBossDamage, EncounterEnd::LogEnd), (3000, 10000));
+        assert_eq!(span(&buffer, EncounterStart::LogStart, EncounterEnd::LastAware), (500, 9000));
+        assert_eq!(span(&buffer, EncounterStart::default(), EncounterEnd::default()), (2000, 8000));
+    }
+
+    #[test]
+    fn fallbacks() {
+        let mut log = Log::new(15438);
+
+        // The boss never enters combat, is never damaged and survives
+        log.npc(1, 15438, "Boss")
+           .player(2, 1, "A", 1)
+           .event(CombatEventV1::new(500).src(2, 2).state_change(CombatStateChange::EnterCombat))
+           .event(CombatEventV1::new(1000).src(1, 1).state_change(CombatStateChange::MaxHealthUpdate))
+           .event(CombatEventV1::new(9000).src(1, 1).state_change(CombatStateChange::ExitCombat))
+           .event(CombatEventV1::new(10000).src(2, 2).state_change(CombatStateChange::ExitCombat));
+
+        let buffer = log.to_bytes();
+
+        assert_eq!(span(&buffer, EncounterStart::EnterCombat, EncounterEnd::BossDeath), (1000, 9000));
+        assert_eq!(span(&buffer, EncounterStart::FirstBossDamage, EncounterEnd::BossDeath), (1000, 9000));
+    }
+}
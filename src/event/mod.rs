@@ -34,6 +34,7 @@ pub trait Event: Clone {
     fn time(&self) -> u64;
     fn into_source(self) -> Option<Self::SourceEvent>;
     fn into_meta(self) -> Option<Self::MetaEvent>;
+    fn into_target(self) -> Option<Self::TargetEvent>;
     fn into_damage(self) -> Option<Self::DamageEvent>;
     fn into_activation(self) -> Option<Self::ActivationEvent>;
     fn into_buff(self) -> Option<Self::BuffEvent>;
@@ -45,6 +46,122 @@ pub trait Event: Clone {
     fn from_any_of<I: IntoIterator<Item=AgentId>>(self, I) -> Option<Self::SourceEvent>;
     fn targeting_agent(self, AgentId) -> Option<Self::TargetEvent>;
     fn targeting_any_of<I: IntoIterator<Item=AgentId>>(self, I) -> Option<Self::TargetEvent>;
+
+    /// Converts the event into an owned [EventKind], `None` if the event is of an unknown kind.
+    fn classify(&self) -> Option<EventKind> {
+        let time = self.time();
+
+        if let Some(m) = self.clone().into_meta() {
+            return Some(EventKind::Meta { time, data: m.into_enum() });
+        }
+
+        let e = self.clone().into_source()?;
+
+        if let Some(change) = e.state_change() {
+            return Some(EventKind::StateChange { time, agent: e.agent(), change });
+        }
+
+        if let Some(a) = e.clone().into_activation() {
+            return Some(EventKind::Activation { time, agent: a.agent(), skill: a.skill(), cast: a.cast() });
+        }
+
+        if let Some(b) = e.clone().into_buff() {
+            return Some(if b.is_remove() {
+                EventKind::BuffRemove {
+                    time,
+                    agent:    b.agent(),
+                    target:   b.target_agent(),
+                    skill:    b.skill(),
+                    removal:  b.removal(),
+                    duration: b.duration(),
+                }
+            }
+            else {
+                EventKind::BuffApply {
+                    time,
+                    agent:     b.agent(),
+                    target:    b.target_agent(),
+                    skill:     b.skill(),
+                    duration:  b.duration(),
+                    overstack: b.overstack(),
+                }
+            });
+        }
+
+        if let Some(d) = e.into_damage() {
+            return Some(EventKind::Damage {
+                time,
+                agent:    d.agent(),
+                target:   d.target_agent(),
+                skill:    d.skill(),
+                damage:   d.damage(),
+                barrier:  d.barrier(),
+                hit_type: d.hit_type(),
+                flanking: d.flanking(),
+                moving:   d.moving(),
+                over90:   d.over90(),
+            });
+        }
+
+        None
+    }
+}
+
+/// Owned representation of an event, see [Event::classify].
+///
+/// As with [Buff], `agent` of a [EventKind::BuffRemove] is the agent losing the buff.
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum EventKind {
+    #[serde(rename_all = "camelCase")]
+    Damage {
+        time:     u64,
+        agent:    AgentId,
+        target:   AgentId,
+        skill:    u16,
+        damage:   i64,
+        barrier:  i64,
+        hit_type: HitType,
+        flanking: bool,
+        moving:   bool,
+        over90:   bool,
+    },
+    #[serde(rename_all = "camelCase")]
+    BuffApply {
+        time:      u64,
+        agent:     AgentId,
+        target:    AgentId,
+        skill:     u16,
+        duration:  u32,
+        overstack: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    BuffRemove {
+        time:     u64,
+        agent:    AgentId,
+        target:   AgentId,
+        skill:    u16,
+        removal:  BuffRemoval,
+        duration: u32,
+    },
+    #[serde(rename_all = "camelCase")]
+    Activation {
+        time:  u64,
+        agent: AgentId,
+        skill: u16,
+        cast:  CastType,
+    },
+    #[serde(rename_all = "camelCase")]
+    StateChange {
+        time:   u64,
+        agent:  AgentId,
+        change: StateChange,
+    },
+    #[serde(rename_all = "camelCase")]
+    Meta {
+        time: u64,
+        data: MetaEventData,
+    },
 }
 
 /// Trait for events which are not tied to any source agent.
@@ -80,6 +197,8 @@ pub trait Buff: Target<SourceEvent=Self, TargetEvent=Self, BuffEvent=Self> {
     fn removal(&self) -> BuffRemoval;
     fn duration(&self) -> u32;
     fn overstack(&self) -> u32;
+    /// Number of stacks removed, zero if the event is not a removal.
+    fn removed_stacks(&self) -> u32;
 
     fn is_remove(&self) -> bool {
         self.removal() != BuffRemoval::None
@@ -95,6 +214,8 @@ pub trait Damage: Target<SourceEvent=Self, TargetEvent=Self, DamageEvent=Self>
     fn moving(&self)   -> bool;
     fn hit_type(&self) -> HitType;
     fn over90(&self)   -> bool;
+    /// Damage absorbed by barrier/shields, not included in [Damage::damage].
+    fn barrier(&self)  -> i64;
 }
 
 /// Wrapper around an event indicating that the event is a meta-event.
@@ -122,11 +243,13 @@ pub struct TargetEvent<T: Event>(T);
 pub struct BuffEvent<T: Event>(T);
 
 /// Data not tied to any agent.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum MetaEventData {
     /// When the log starts, server unix timestamp, local unix timestamp, arcdpsId
+    #[serde(rename_all = "camelCase")]
     LogStart { server: u32, local: u32, arcdps_id: u64 },
     /// When the log ends, server unix timestamp, local unix timestamp, arcdpsId
+    #[serde(rename_all = "camelCase")]
     LogEnd   { server: u32, local: u32, arcdps_id: u64 },
     /// The language used of the client
     Language(Language),
@@ -137,7 +260,7 @@ pub enum MetaEventData {
 }
 
 /// The type of damaging hit.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum HitType {
     Condi,
     Normal,
@@ -163,7 +286,7 @@ impl HitType {
 }
 
 /// State updates for agents.
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Serialize, Deserialize)]
 pub enum StateChange {
     /// Agent entered combat.
     EnterCombat(u64),
@@ -195,13 +318,59 @@ pub enum StateChange {
     Velocity { x: f32, y: f32, z: f32 },
     /// Agent facing has been updated.
     Facing   { x: f32, y: f32 },
-    /// Happens once per agent on start
-    // TODO: What is this? Should have more data
+    /// A buff which was already active when the log started, skill id, remaining duration in
+    /// milliseconds, the agent with the buff and the agent which applied it, empty if unknown.
+    #[serde(rename_all = "camelCase")]
+    BuffInitial { skill: u16, duration: u32, target: AgentId, source: AgentId },
+}
+
+/// The kind of a [StateChange] without any associated data.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum StateChangeKind {
+    EnterCombat,
+    ExitCombat,
+    ChangeUp,
+    ChangeDead,
+    ChangeDown,
+    Spawn,
+    Despawn,
+    HealthUpdate,
+    WeaponSwap,
+    MaxHealthUpdate,
+    PointOfView,
+    Reward,
+    Position,
+    Velocity,
+    Facing,
     BuffInitial,
 }
 
+impl StateChange {
+    /// The kind of state change, without its data.
+    pub fn kind(&self) -> StateChangeKind {
+        match *self {
+            StateChange::EnterCombat(_)     => StateChangeKind::EnterCombat,
+            StateChange::ExitCombat         => StateChangeKind::ExitCombat,
+            StateChange::ChangeUp           => StateChangeKind::ChangeUp,
+            StateChange::ChangeDead         => StateChangeKind::ChangeDead,
+            StateChange::ChangeDown         => StateChangeKind::ChangeDown,
+            StateChange::Spawn              => StateChangeKind::Spawn,
+            StateChange::Despawn            => StateChangeKind::Despawn,
+            StateChange::HealthUpdate(_)    => StateChangeKind::HealthUpdate,
+            StateChange::WeaponSwap         => StateChangeKind::WeaponSwap,
+            StateChange::MaxHealthUpdate(_) => StateChangeKind::MaxHealthUpdate,
+            StateChange::PointOfView        => StateChangeKind::PointOfView,
+            StateChange::Reward(..)         => StateChangeKind::Reward,
+            StateChange::Position { .. }    => StateChangeKind::Position,
+            StateChange::Velocity { .. }    => StateChangeKind::Velocity,
+            StateChange::Facing { .. }      => StateChangeKind::Facing,
+            StateChange::BuffInitial { .. } => StateChangeKind::BuffInitial,
+        }
+    }
+}
+
 /// Type of skill animation activation.
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CastType {
     /// Normal cast, expected duration
     Normal(u32),
@@ -227,3 +396,113 @@ impl CastType {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use serde_json;
+
+    use event::raw::CombatActivation;
+    use event::raw::CombatBuffRemove;
+    use event::raw::CombatEventV1;
+    use event::raw::CombatStateChange;
+    use event::raw::HitResult;
+
+    fn classify(e: CombatEventV1) -> EventKind {
+        let kind = (&e).classify().expect("Event was not classified");
+        let json = serde_json::to_string(&kind).unwrap();
+
+        assert_eq!(serde_json::from_str::<EventKind>(&json).unwrap(), kind);
+
+        kind
+    }
+
+    #[test]
+    fn classify_damage() {
+        assert_eq!(classify(CombatEventV1::new(1000).src(1, 1).dst(2, 2).skill(100).damage(500, 20, HitResult::Crit)), EventKind::Damage {
+            time:     1000,
+            agent:    AgentId::new(1),
+            target:   AgentId::new(2),
+            skill:    100,
+            damage:   500,
+            barrier:  20,
+            hit_type: HitType::Crit,
+            flanking: false,
+            moving:   false,
+            over90:   false,
+        });
+
+        match classify(CombatEventV1::new(1000).src(1, 1).dst(2, 2).condi_damage(736, 200)) {
+            EventKind::Damage { damage: 200, hit_type: HitType::Condi, .. } => {},
+            k => panic!("Expected condition damage, got {:?}", k),
+        }
+    }
+
+    #[test]
+    fn classify_buff_apply() {
+        assert_eq!(classify(CombatEventV1::new(2000).src(1, 1).dst(2, 2).buff_apply(740, 5000)), EventKind::BuffApply {
+            time:      2000,
+            agent:     AgentId::new(1),
+            target:    AgentId::new(2),
+            skill:     740,
+            duration:  5000,
+            overstack: 0,
+        });
+    }
+
+    #[test]
+    fn classify_buff_remove() {
+        // The source is the agent losing the buff
+        assert_eq!(classify(CombatEventV1::new(3000).src(2, 2).dst(1, 1).buff_remove(740, 3000, CombatBuffRemove::Single)), EventKind::BuffRemove {
+            time:     3000,
+            agent:    AgentId::new(2),
+            target:   AgentId::new(1),
+            skill:    740,
+            removal:  BuffRemoval::Single,
+            duration: 3000,
+        });
+    }
+
+    #[test]
+    fn classify_activation() {
+        assert_eq!(classify(CombatEventV1::new(4000).src(1, 1).skill(100).value(800).activation(100, CombatActivation::Normal)), EventKind::Activation {
+            time:  4000,
+            agent: AgentId::new(1),
+            skill: 100,
+            cast:  CastType::Normal(800),
+        });
+    }
+
+    #[test]
+    fn classify_state_change() {
+        assert_eq!(classify(CombatEventV1::new(5000).src(1, 1).state_change(CombatStateChange::ChangeDown)), EventKind::StateChange {
+            time:   5000,
+            agent:  AgentId::new(1),
+            change: StateChange::ChangeDown,
+        });
+    }
+
+    #[test]
+    fn classify_meta() {
+        assert_eq!(classify(CombatEventV1::new(0).src(42, 0).value(1_500_000_000).state_change(CombatStateChange::LogStart)), EventKind::Meta {
+            time: 0,
+            data: MetaEventData::LogStart { server: 1_500_000_000, local: 0, arcdps_id: 42 },
+        });
+    }
+
+    #[test]
+    fn serialized_names() {
+        let kind = classify(CombatEventV1::new(1000).src(1, 1).dst(2, 2).skill(100).damage(500, 0, HitResult::Normal));
+        let json = serde_json::to_value(&kind).unwrap();
+
+        assert_eq!(json["type"], "damage");
+        assert_eq!(json["hitType"], "Normal");
+
+        let kind = classify(CombatEventV1::new(0).src(42, 0).state_change(CombatStateChange::LogEnd));
+        let json = serde_json::to_value(&kind).unwrap();
+
+        assert_eq!(json["type"], "meta");
+        assert_eq!(json["data"]["LogEnd"]["arcdpsId"], 42);
+    }
+}
@@ -21,6 +21,9 @@ use InstanceId;
 use Profession;
 use SpeciesId;
 
+#[cfg(test)]
+use synthetic::Event as SyntheticEvent;
+
 use std::cmp;
 use std::fmt;
 use std::mem;
@@ -192,7 +195,7 @@ impl Skill {
 }
 
 /// Language constants.
-#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum Language {
     English = 0,
     French  = 2,
@@ -251,6 +254,25 @@ pub enum HitResult {
     DowningBlow = 9,
 }
 
+impl HitResult {
+    /// Unknown results are treated as normal hits.
+    #[inline]
+    pub fn from_u8(result: u8) -> HitResult {
+        match result {
+            1 => HitResult::Crit,
+            2 => HitResult::Glance,
+            3 => HitResult::Block,
+            4 => HitResult::Evade,
+            5 => HitResult::Interrupt,
+            6 => HitResult::Absorb,
+            7 => HitResult::Blind,
+            8 => HitResult::KillingBlow,
+            9 => HitResult::DowningBlow,
+            _ => HitResult::Normal,
+        }
+    }
+}
+
 /// Skill activation types types.
 #[repr(u8)]
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -323,7 +345,7 @@ pub enum CombatStateChange {
 
 /// Buff removals.
 #[repr(u8)]
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
 pub enum CombatBuffRemove {
     /// Not used - not this kind of event
     None   = 0,
@@ -342,48 +364,49 @@ pub enum CombatBuffRemove {
 #[derive(Debug, Clone, Copy)]
 pub struct CombatEventV1 {
     // timegettime() at time of event
-    time:              u64,
+    pub(crate) time:              u64,
     // Unique identifier
-    src_agent:         u64,
+    pub(crate) src_agent:         u64,
     // Unique identifier
-    dst_agent:         u64,
+    pub(crate) dst_agent:         u64,
     // Event-specific
-    value:             i32,
+    pub(crate) value:             i32,
     // Estimated buff damage. Zero on application event
-    buff_dmg:          i32,
+    pub(crate) buff_dmg:          i32,
     // Estimated overwritten stack duration for buff application
-    overstack:         u16,
+    pub(crate) overstack:         u16,
     // Skill ID
-    skill_id:          u16,
+    pub(crate) skill_id:          u16,
     // Agent map instance id
-    src_instid:        u16,
+    pub(crate) src_instid:        u16,
     // Agent map instance id
-    dst_instid:        u16,
+    pub(crate) dst_instid:        u16,
     // Master source agent map instance id if source is a minion/pet
-    src_master_instid: u16,
-    _pad8:             u64,
-    _pad1:             u8,
-    iff:               IFF,
+    pub(crate) src_master_instid: u16,
+    pub(crate) _pad8:             u64,
+    pub(crate) _pad1:             u8,
+    pub(crate) iff:               IFF,
     // Buff application, removal, or damage event
-    buff:              u8,
-    result:            HitResult,
-    is_activation:     CombatActivation,
+    pub(crate) buff:              u8,
+    // HitResult for damage, the number of stacks removed for buff removals of all stacks
+    pub(crate) result:            u8,
+    pub(crate) is_activation:     CombatActivation,
     // buff removed. src=relevant, dst=caused it (for strips/cleanses). from cbtr enum
-    is_buffremove:     CombatBuffRemove,
+    pub(crate) is_buffremove:     CombatBuffRemove,
     // source agent health was over 90%
-    is_src_ninety:     u8,
+    pub(crate) is_src_ninety:     u8,
     // target agent health was under 50%
-    is_dst_fifty:      u8,
+    pub(crate) is_dst_fifty:      u8,
     // source agent was moving
-    is_src_moving:     u8,
-    is_statechange:    CombatStateChange,
+    pub(crate) is_src_moving:     u8,
+    pub(crate) is_statechange:    CombatStateChange,
     // If source was flanking target
-    is_flanking:       u8,
+    pub(crate) is_flanking:       u8,
     // All or part damage was vs barrier/shield
-    is_shields:        u8,
+    pub(crate) is_shields:        u8,
     // 2018-07-10: zero if on-tick dmg, non-zero otherwise (buff only)
-    is_offcycle:       u8,
-    _pad2:             u8,
+    pub(crate) is_offcycle:       u8,
+    pub(crate) _pad2:             u8,
 }
 
 impl CombatEventV1 {
@@ -412,6 +435,62 @@ impl CombatEventV1 {
     }
 }
 
+/// Shorthands for building events in tests, see [synthetic::Event](../../synthetic/struct.Event.html).
+#[cfg(test)]
+impl CombatEventV1 {
+    pub(crate) fn new(time: u64) -> Self {
+        SyntheticEvent::new(time).into()
+    }
+
+    pub(crate) fn src(self, agent: u64, instid: u16) -> Self {
+        SyntheticEvent::from(self).src(agent, instid).into()
+    }
+
+    pub(crate) fn dst(self, agent: u64, instid: u16) -> Self {
+        SyntheticEvent::from(self).dst(agent, instid).into()
+    }
+
+    pub(crate) fn master(self, instid: u16) -> Self {
+        SyntheticEvent::from(self).master(instid).into()
+    }
+
+    pub(crate) fn skill(self, skill: u16) -> Self {
+        SyntheticEvent::from(self).skill(skill).into()
+    }
+
+    pub(crate) fn value(self, value: i32) -> Self {
+        SyntheticEvent::from(self).value(value).into()
+    }
+
+    pub(crate) fn damage(self, damage: i32, barrier: u16, result: HitResult) -> Self {
+        SyntheticEvent::from(self).damage(damage, barrier, result).into()
+    }
+
+    pub(crate) fn condi_damage(self, skill: u16, damage: i32) -> Self {
+        SyntheticEvent::from(self).condi_damage(skill, damage).into()
+    }
+
+    pub(crate) fn buff_apply(self, skill: u16, duration: i32) -> Self {
+        SyntheticEvent::from(self).buff_apply(skill, duration).into()
+    }
+
+    pub(crate) fn buff_remove(self, skill: u16, duration: i32, removal: CombatBuffRemove) -> Self {
+        SyntheticEvent::from(self).buff_remove(skill, duration, removal).into()
+    }
+
+    pub(crate) fn removed_stacks(self, stacks: u8) -> Self {
+        SyntheticEvent::from(self).removed_stacks(stacks).into()
+    }
+
+    pub(crate) fn activation(self, skill: u16, activation: CombatActivation) -> Self {
+        SyntheticEvent::from(self).activation(skill, activation).into()
+    }
+
+    pub(crate) fn state_change(self, state: CombatStateChange) -> Self {
+        SyntheticEvent::from(self).state_change(state).into()
+    }
+}
+
 impl<'a> Event for &'a CombatEventV1 {
     type MetaEvent = MetaEvent<&'a CombatEventV1>;
     type SourceEvent = SourceEvent<&'a CombatEventV1>;
@@ -445,6 +524,16 @@ impl<'a> Event for &'a CombatEventV1 {
         }
     }
 
+    #[inline]
+    fn into_target(self) -> Option<Self::TargetEvent> {
+        if self.is_statechange == CombatStateChange::None && self.is_activation == CombatActivation::None {
+            Some(TargetEvent(self))
+        }
+        else {
+            None
+        }
+    }
+
     #[inline]
     fn into_damage(self) -> Option<Self::DamageEvent> {
         match (self.is_statechange, self.is_activation, self.is_buffremove, self.buff > 0 && self.buff_dmg == 0) {
@@ -563,6 +652,11 @@ impl<'a> Event for MetaEvent<&'a CombatEventV1> {
         None
     }
 
+    #[inline]
+    fn into_target(self) -> Option<Self::TargetEvent> {
+        None
+    }
+
     #[inline]
     fn into_damage(self) -> Option<Self::DamageEvent> {
         None
@@ -649,6 +743,16 @@ impl<'a> Event for SourceEvent<&'a CombatEventV1> {
         Some(self)
     }
 
+    #[inline]
+    fn into_target(self) -> Option<Self::TargetEvent> {
+        if self.0.is_statechange == CombatStateChange::None && self.0.is_activation == CombatActivation::None {
+            Some(TargetEvent(self.0))
+        }
+        else {
+            None
+        }
+    }
+
     #[inline]
     fn into_damage(self) -> Option<Self::DamageEvent> {
         match (self.0.is_statechange, self.0.is_activation, self.0.is_buffremove, self.0.buff > 0 && self.0.buff_dmg == 0) {
@@ -773,10 +877,17 @@ impl<'a> Source for SourceEvent<&'a CombatEventV1> {
             CombatStateChange::MaxHealthUpdate => Some(StateChange::MaxHealthUpdate(self.0.dst_agent)),
             CombatStateChange::PointOfView     => Some(StateChange::PointOfView),
             CombatStateChange::Reward          => Some(StateChange::Reward(self.0.dst_agent, self.0.value as u32)),
-            CombatStateChange::BuffInitial     => {
-                // TODO: What is this?
-                Some(StateChange::BuffInitial)
-            },
+            // Laid out like a buff application: value is the remaining duration in milliseconds and
+            // src applied the buff to dst. Buffs which do not run out during the encounter have
+            // durations like i32::MAX, a negative remaining duration has no other meaning so it
+            // is treated the same instead of dropping a buff the agent has.
+            CombatStateChange::BuffInitial     => Some(StateChange::BuffInitial {
+                skill:    self.0.skill_id,
+                duration: if self.0.value < 0 { i32::MAX as u32 } else { self.0.value as u32 },
+                // Fall back to the source if there is no target, the source is then unknown
+                target:   if self.0.dst_agent != 0 { self.0.dst_agent() } else { self.0.src_agent() },
+                source:   if self.0.dst_agent != 0 { self.0.src_agent() } else { AgentId::empty() },
+            }),
             CombatStateChange::Position        => {
                 let pos: &[f32; 3] = unsafe { mem::transmute(&self.0.dst_agent) };
 
@@ -824,6 +935,11 @@ impl<'a> Event for TargetEvent<&'a CombatEventV1> {
         Some(self)
     }
 
+    #[inline]
+    fn into_target(self) -> Option<Self::TargetEvent> {
+        Some(self)
+    }
+
     #[inline]
     fn into_damage(self) -> Option<Self::DamageEvent> {
         // Target events should not have any statechanges or activations
@@ -982,6 +1098,12 @@ impl<'a> Event for ActivationEvent<&'a CombatEventV1> {
         Some(self)
     }
 
+    #[inline]
+    fn into_target(self) -> Option<Self::TargetEvent> {
+        // Activation events do not have a target
+        None
+    }
+
     #[inline]
     fn into_damage(self) -> Option<Self::DamageEvent> {
         // Skill activations can't do damage
@@ -1121,6 +1243,11 @@ impl<'a> Event for DamageEvent<&'a CombatEventV1> {
         Some(self)
     }
 
+    #[inline]
+    fn into_target(self) -> Option<Self::TargetEvent> {
+        Some(self)
+    }
+
     #[inline]
     fn into_damage(self) -> Option<Self::DamageEvent> {
         Some(self)
@@ -1261,7 +1388,7 @@ impl<'a> Damage for DamageEvent<&'a CombatEventV1> {
 
     #[inline]
     fn hit_type(&self) -> HitType {
-        match (self.0.buff > 0, self.0.result) {
+        match (self.0.buff > 0, HitResult::from_u8(self.0.result)) {
             (true, _)                      => HitType::Condi,
             (_,    HitResult::Normal)      => HitType::Normal,
             (_,    HitResult::Crit)        => HitType::Crit,
@@ -1280,6 +1407,17 @@ impl<'a> Damage for DamageEvent<&'a CombatEventV1> {
     fn over90(&self) -> bool {
         self.0.is_src_ninety > 0
     }
+
+    #[inline]
+    fn barrier(&self) -> i64 {
+        // The overstack value holds the amount absorbed by the barrier for damage events
+        if self.0.is_shields > 0 {
+            self.0.overstack as i64
+        }
+        else {
+            0
+        }
+    }
 }
 
 // DamageEvent end
@@ -1309,6 +1447,11 @@ impl<'a> Event for BuffEvent<&'a CombatEventV1> {
         Some(self)
     }
 
+    #[inline]
+    fn into_target(self) -> Option<Self::TargetEvent> {
+        Some(self)
+    }
+
     #[inline]
     fn into_damage(self) -> Option<Self::DamageEvent> {
         None
@@ -1440,6 +1583,16 @@ impl<'a> Buff for BuffEvent<&'a CombatEventV1> {
     fn removal(&self) -> CombatBuffRemove {
         self.0.is_buffremove
     }
+
+    #[inline]
+    fn removed_stacks(&self) -> u32 {
+        match self.0.is_buffremove {
+            CombatBuffRemove::None => 0,
+            // A zero count is treated as a single stack
+            CombatBuffRemove::All  => cmp::max(self.0.result, 1) as u32,
+            _                      => 1,
+        }
+    }
 }
 
 // BuffEvent end
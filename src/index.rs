@@ -0,0 +1,231 @@
+use event::Activation;
+use event::Buff;
+use event::Damage;
+use event::Event;
+use event::Source;
+use event::Target;
+use event::raw::CombatEventV1;
+
+use fnv::FnvHashMap;
+
+use std::cmp;
+use std::cmp::Ordering;
+
+use Agent;
+use AgentId;
+use InstanceId;
+use Metadata;
+
+/// Index of the events in a log, built once to avoid scanning all events for every lookup.
+///
+/// All positions are indices into [Metadata::events], in time-order.
+#[derive(Debug, Clone)]
+pub struct EventIndex<'a> {
+    events:  &'a [CombatEventV1],
+    /// Events sourced by the agent or any of its gadgets/minions.
+    sources: FnvHashMap<AgentId, Vec<u32>>,
+    /// Events targeting the agent.
+    targets: FnvHashMap<AgentId, Vec<u32>>,
+    /// Damage, buff and activation events per skill.
+    skills:  FnvHashMap<u16, Vec<u32>>,
+}
+
+impl<'a> EventIndex<'a> {
+    pub fn new(meta: &Metadata<'a>) -> Self {
+        let events = meta.events();
+        // TODO: Are InstanceIds reused?
+        let masters: FnvHashMap<InstanceId, AgentId> = meta.agents()
+                                                           .iter()
+                                                           .filter(|a| a.instance_id() != InstanceId::empty())
+                                                           .map(|a| (a.instance_id(), a.id()))
+                                                           .collect();
+        let mut sources = FnvHashMap::<AgentId, Vec<u32>>::default();
+        let mut targets = FnvHashMap::<AgentId, Vec<u32>>::default();
+        let mut skills  = FnvHashMap::<u16, Vec<u32>>::default();
+
+        for (i, e) in events.iter().enumerate() {
+            let i = i as u32;
+            let e = match e.into_source() {
+                Some(e) => e,
+                None    => continue,
+            };
+
+            sources.entry(e.agent()).or_default().push(i);
+
+            if let Some(&master) = e.master_instance().and_then(|m| masters.get(&m)) {
+                if master != e.agent() {
+                    sources.entry(master).or_default().push(i);
+                }
+            }
+
+            if let Some(t) = e.clone().into_target() {
+                targets.entry(t.target_agent()).or_default().push(i);
+            }
+
+            let skill = e.clone().into_damage().map(|d| d.skill())
+                         .or_else(|| e.clone().into_buff().map(|b| b.skill()))
+                         .or_else(|| e.clone().into_activation().map(|a| a.skill()));
+
+            if let Some(skill) = skill {
+                skills.entry(skill).or_default().push(i);
+            }
+        }
+
+        EventIndex {
+            events,
+            sources,
+            targets,
+            skills,
+        }
+    }
+
+    /// Positions of the events sourced by the agent or any of its gadgets/minions.
+    #[inline]
+    pub fn sourced_by(&self, agent: &Agent) -> &[u32] {
+        self.sources.get(&agent.id()).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    /// Positions of the events targeting the agent.
+    #[inline]
+    pub fn targeting(&self, agent: &Agent) -> &[u32] {
+        self.targets.get(&agent.id()).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    /// Positions of the damage, buff and activation events using the skill.
+    #[inline]
+    pub fn with_skill(&self, skill: u16) -> &[u32] {
+        self.skills.get(&skill).map(|v| &v[..]).unwrap_or(&[])
+    }
+
+    /// The positions of events between `start` and `end`, inclusive, relative milliseconds.
+    ///
+    /// `positions` has to be one of the position lists of this index.
+    pub fn during<'b>(&self, positions: &'b [u32], start: u64, end: u64) -> &'b [u32] {
+        let from = positions.partition_point(|&i| self.get(i).time() < start);
+        let to   = positions.partition_point(|&i| self.get(i).time() <= end);
+
+        &positions[from..cmp::max(from, to)]
+    }
+
+    /// The event at the given position.
+    #[inline]
+    pub fn get(&self, position: u32) -> &'a CombatEventV1 {
+        &self.events[position as usize]
+    }
+
+    /// The events at the given positions.
+    #[inline]
+    pub fn events<'b>(&'b self, positions: &'b [u32]) -> impl Iterator<Item=&'a CombatEventV1> + 'b {
+        positions.iter().map(move |&i| self.get(i))
+    }
+
+    /// Events sourced by the agent, its gadgets/minions, or targeting the agent, in time-order.
+    ///
+    /// Equivalent to [EventIteratorExt::related_to](trait.EventIteratorExt.html) over all events.
+    pub fn related_to<'b>(&'b self, agent: &Agent) -> impl Iterator<Item=&'a CombatEventV1> + 'b {
+        let mut a = self.sourced_by(agent).iter().peekable();
+        let mut b = self.targeting(agent).iter().peekable();
+        let mut positions = Vec::with_capacity(a.len() + b.len());
+
+        // Merge the two sorted position lists
+        loop {
+            let next = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => match x.cmp(y) {
+                    Ordering::Less    => a.next(),
+                    Ordering::Greater => b.next(),
+                    Ordering::Equal   => { b.next(); a.next() },
+                },
+                (Some(_), None) => a.next(),
+                (None, Some(_)) => b.next(),
+                (None, None)    => break,
+            };
+
+            positions.extend(next);
+        }
+
+        positions.into_iter().map(move |i| self.get(i))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use EventIteratorExt;
+
+    use event::raw;
+    use event::raw::CombatActivation;
+    use event::raw::CombatEventV1;
+    use event::raw::HitResult;
+    use synthetic::Log;
+
+    fn log() -> Vec<u8> {
+        let mut log = Log::new(15438);
+
+        log.npc(1, 15438, "Boss")
+           .player(2, 1, "A", 1)
+           .player(3, 2, "B", 1)
+           .npc(4, 6001, "Minion")
+           // Instance ids are picked up from the first event of each agent
+           .event(CombatEventV1::new(1000).src(1, 1).dst(2, 2).skill(100).damage(300, 0, HitResult::Normal))
+           .event(CombatEventV1::new(1000).src(2, 2).activation(200, CombatActivation::Normal))
+           .event(CombatEventV1::new(1000).src(3, 3).activation(300, CombatActivation::Normal))
+           .event(CombatEventV1::new(2000).src(2, 2).dst(1, 1).skill(200).damage(500, 0, HitResult::Crit))
+           .event(CombatEventV1::new(3000).src(2, 2).dst(3, 3).buff_apply(740, 5000))
+           .event(CombatEventV1::new(4000).src(4, 4).master(2).dst(1, 1).skill(400).damage(100, 0, HitResult::Normal))
+           .event(CombatEventV1::new(5000).src(3, 3).dst(1, 1).skill(200).damage(200, 0, HitResult::Normal))
+           .event(CombatEventV1::new(6000).src(1, 1).dst(3, 3).skill(100).damage(300, 0, HitResult::Normal));
+
+        log.to_bytes()
+    }
+
+    fn times<'a, I: Iterator<Item=&'a CombatEventV1>>(iter: I) -> Vec<u64> {
+        iter.map(|e| e.time()).collect()
+    }
+
+    #[test]
+    fn agents() {
+        let buffer = log();
+        let evtc   = raw::transmute(&buffer);
+        let meta   = Metadata::new(&evtc);
+        let index  = EventIndex::new(&meta);
+        let a      = &meta.agents()[1];
+        let b      = &meta.agents()[2];
+
+        assert_eq!(a.id(), AgentId::new(2));
+        // Includes the damage of the minion
+        assert_eq!(times(index.events(index.sourced_by(a))), [1000, 2000, 3000, 4000]);
+        assert_eq!(times(index.events(index.targeting(a))), [1000]);
+        assert_eq!(times(index.events(index.targeting(b))), [3000, 6000]);
+        assert_eq!(times(index.related_to(a)), [1000, 1000, 2000, 3000, 4000]);
+        assert_eq!(times(index.related_to(b)), meta.events().iter().related_to(b).map(|e| e.time()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn skills() {
+        let buffer = log();
+        let evtc   = raw::transmute(&buffer);
+        let meta   = Metadata::new(&evtc);
+        let index  = EventIndex::new(&meta);
+
+        // Damage and activations
+        assert_eq!(times(index.events(index.with_skill(200))), [1000, 2000, 5000]);
+        assert_eq!(times(index.events(index.with_skill(740))), [3000]);
+        assert!(index.with_skill(999).is_empty());
+    }
+
+    #[test]
+    fn time_range() {
+        let buffer = log();
+        let evtc   = raw::transmute(&buffer);
+        let meta   = Metadata::new(&evtc);
+        let index  = EventIndex::new(&meta);
+        let a      = &meta.agents()[1];
+
+        assert_eq!(times(index.events(index.during(index.sourced_by(a), 2000, 4000))), [2000, 3000, 4000]);
+        assert_eq!(times(index.events(index.during(index.sourced_by(a), 2500, 3500))), [3000]);
+        assert_eq!(times(index.events(index.during(index.with_skill(100), 0, 5999))), [1000]);
+        assert!(index.during(index.sourced_by(a), 7000, 8000).is_empty());
+        assert!(index.during(index.sourced_by(a), 4000, 1000).is_empty());
+    }
+}
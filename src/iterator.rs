@@ -3,7 +3,15 @@ use AgentId;
 use InstanceId;
 use Event;
 
+use event::Activation;
+use event::Buff;
+use event::Damage;
+use event::HitType;
+use event::Source;
+use event::StateChangeKind;
+
 use std::iter::Iterator;
+use std::ops::Range;
 
 pub trait EventIteratorExt: Iterator
   where Self: Sized {
@@ -56,6 +64,62 @@ pub trait EventIteratorExt: Iterator
             inner: self,
         }
     }
+
+    /// Events with a timestamp within the range, end exclusive.
+    #[inline]
+    fn during(self, range: Range<u64>) -> During<Self> {
+        During {
+            range,
+            inner: self,
+        }
+    }
+
+    /// Damage, buff and activation events using the skill.
+    #[inline]
+    fn with_skill(self, skill: u16) -> WithSkill<Self> {
+        WithSkill {
+            skill,
+            inner: self,
+        }
+    }
+
+    #[inline]
+    fn with_hit_type(self, hit_type: HitType) -> WithHitType<Self> {
+        WithHitType {
+            hit_type,
+            inner: self,
+        }
+    }
+
+    #[inline]
+    fn damage_only(self) -> DamageOnly<Self> {
+        DamageOnly {
+            inner: self,
+        }
+    }
+
+    /// Buff applications and removals.
+    #[inline]
+    fn buffs_only(self) -> BuffsOnly<Self> {
+        BuffsOnly {
+            inner: self,
+        }
+    }
+
+    #[inline]
+    fn activations_only(self) -> ActivationsOnly<Self> {
+        ActivationsOnly {
+            inner: self,
+        }
+    }
+
+    #[inline]
+    fn state_changes(self, kind: StateChangeKind) -> StateChanges<Self> {
+        StateChanges {
+            kind,
+            inner: self,
+        }
+    }
 }
 
 impl<I: Iterator> EventIteratorExt for I {}
@@ -181,4 +245,186 @@ impl<I: Iterator<Item=T>, T: Event> Iterator for RelatingToAgentIterator<I> {
 
         None
     }
-}
\ No newline at end of file
+}
+
+pub struct During<I> {
+    range: Range<u64>,
+    inner: I,
+}
+
+impl<I: Iterator<Item=T>, T: Event> Iterator for During<I> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let range = &self.range;
+
+        self.inner.by_ref().find(|e| range.start <= e.time() && e.time() < range.end)
+    }
+}
+
+pub struct WithSkill<I> {
+    skill: u16,
+    inner: I,
+}
+
+impl<I: Iterator<Item=T>, T: Event> Iterator for WithSkill<I> {
+    type Item = T::SourceEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for e in self.inner.by_ref() {
+            if let Some(e) = e.into_source() {
+                let skill = e.clone().into_damage().map(|d| d.skill())
+                             .or_else(|| e.clone().into_buff().map(|b| b.skill()))
+                             .or_else(|| e.clone().into_activation().map(|a| a.skill()));
+
+                if skill == Some(self.skill) {
+                    return Some(e);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+pub struct WithHitType<I> {
+    hit_type: HitType,
+    inner:    I,
+}
+
+impl<I: Iterator<Item=T>, T: Event> Iterator for WithHitType<I> {
+    type Item = T::DamageEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for e in self.inner.by_ref() {
+            if let Some(e) = e.into_damage() {
+                if e.hit_type() == self.hit_type {
+                    return Some(e);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+pub struct DamageOnly<I> {
+    inner: I,
+}
+
+impl<I: Iterator<Item=T>, T: Event> Iterator for DamageOnly<I> {
+    type Item = T::DamageEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for e in self.inner.by_ref() {
+            if let Some(e) = e.into_damage() {
+                return Some(e);
+            }
+        }
+
+        None
+    }
+}
+
+pub struct BuffsOnly<I> {
+    inner: I,
+}
+
+impl<I: Iterator<Item=T>, T: Event> Iterator for BuffsOnly<I> {
+    type Item = T::BuffEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for e in self.inner.by_ref() {
+            if let Some(e) = e.into_buff() {
+                return Some(e);
+            }
+        }
+
+        None
+    }
+}
+
+pub struct ActivationsOnly<I> {
+    inner: I,
+}
+
+impl<I: Iterator<Item=T>, T: Event> Iterator for ActivationsOnly<I> {
+    type Item = T::ActivationEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for e in self.inner.by_ref() {
+            if let Some(e) = e.into_activation() {
+                return Some(e);
+            }
+        }
+
+        None
+    }
+}
+
+pub struct StateChanges<I> {
+    kind:  StateChangeKind,
+    inner: I,
+}
+
+impl<I: Iterator<Item=T>, T: Event> Iterator for StateChanges<I> {
+    type Item = T::SourceEvent;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for e in self.inner.by_ref() {
+            if let Some(e) = e.into_source() {
+                if e.state_change().map(|s| s.kind()) == Some(self.kind) {
+                    return Some(e);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use event::raw::CombatActivation;
+    use event::raw::CombatBuffRemove;
+    use event::raw::CombatEventV1;
+    use event::raw::CombatStateChange;
+    use event::raw::HitResult;
+
+    fn events() -> Vec<CombatEventV1> {
+        vec![
+            CombatEventV1::new(1000).src(1, 0).dst(2, 0).skill(100).damage(500, 0, HitResult::Normal),
+            CombatEventV1::new(2000).src(1, 0).dst(2, 0).skill(100).damage(800, 0, HitResult::Crit),
+            CombatEventV1::new(3000).src(1, 0).dst(2, 0).condi_damage(736, 200),
+            CombatEventV1::new(4000).src(1, 0).dst(2, 0).buff_apply(736, 5000),
+            CombatEventV1::new(5000).src(2, 0).dst(1, 0).buff_remove(736, 2000, CombatBuffRemove::All),
+            CombatEventV1::new(6000).src(1, 0).activation(100, CombatActivation::Normal),
+            CombatEventV1::new(7000).src(1, 0).state_change(CombatStateChange::ChangeDead),
+            CombatEventV1::new(8000).src(1, 0).state_change(CombatStateChange::ChangeUp),
+        ]
+    }
+
+    fn times<I: Iterator<Item=u64>>(iter: I) -> Vec<u64> {
+        iter.collect()
+    }
+
+    #[test]
+    fn adaptors() {
+        let events = events();
+
+        assert_eq!(times(events.iter().during(2000..5000).map(|e| e.time())), vec![2000, 3000, 4000]);
+        assert_eq!(times(events.iter().with_skill(100).map(|e| e.time())), vec![1000, 2000, 6000]);
+        assert_eq!(times(events.iter().with_skill(736).map(|e| e.time())), vec![3000, 4000, 5000]);
+        assert_eq!(times(events.iter().with_hit_type(HitType::Crit).map(|e| e.time())), vec![2000]);
+        assert_eq!(times(events.iter().with_hit_type(HitType::Condi).map(|e| e.time())), vec![3000]);
+        assert_eq!(times(events.iter().damage_only().map(|e| e.time())), vec![1000, 2000, 3000]);
+        assert_eq!(times(events.iter().buffs_only().map(|e| e.time())), vec![4000, 5000]);
+        assert_eq!(times(events.iter().activations_only().map(|e| e.time())), vec![6000]);
+        assert_eq!(times(events.iter().state_changes(StateChangeKind::ChangeDead).map(|e| e.time())), vec![7000]);
+
+        // Adaptors compose
+        assert_eq!(times(events.iter().during(0..3000).damage_only().with_hit_type(HitType::Normal).map(|e| e.time())), vec![1000]);
+    }
+}
@@ -3,13 +3,25 @@ extern crate serde;
 #[macro_use]
 extern crate serde_derive;
 extern crate fnv;
+extern crate evtc_derive;
+#[cfg(feature = "parallel")]
+extern crate rayon;
+#[cfg(test)]
+extern crate serde_json;
 
 mod metadata;
+mod encounter;
+mod index;
 mod iterator;
+mod target;
 
+pub mod dispatch;
 pub mod event;
+pub mod phase;
 pub mod statistics;
+pub mod synthetic;
 pub mod buff;
+pub mod time;
 pub mod timeseries;
 
 pub use event::*;
@@ -22,12 +34,19 @@ use std::mem;
 use std::fmt;
 use std::u64;
 
+pub use encounter::Encounter;
+pub use encounter::EncounterEnd;
+pub use encounter::EncounterStart;
+
+pub use index::EventIndex;
 pub use iterator::EventIteratorExt;
 
 pub use metadata::Agent;
 pub use metadata::Metadata;
 pub use metadata::SkillList;
 
+pub use target::TargetSet;
+
 macro_rules! const_assert {
     ($($condition:expr),+ $(,)*) => {
         let _ = [(); 0 - !($($condition)&&+) as usize];
@@ -41,7 +60,7 @@ macro_rules! const_assert {
 }
 
 /// An id of an agent present in the encounter
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash)]
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize, Deserialize)]
 pub struct AgentId(u64);
 
 const_assert!(AgentIdSize; mem::size_of::<AgentId>() == 8);
@@ -106,7 +125,7 @@ impl InstanceId {
 }
 
 /// An id representing the type of enemy/gadget an agent is.
-#[derive(Clone, Copy, Eq, PartialEq, Debug, Hash, Serialize)]
+#[derive(Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Debug, Hash, Serialize)]
 pub struct SpeciesId(u16);
 
 const_assert!(SpeciesIdSize; mem::size_of::<SpeciesId>() == 2);
@@ -339,16 +358,17 @@ impl TimeEntry {
 /// The time-series data for a player
 #[derive(Debug, Clone)]
 pub struct TimeSeries {
-    series: Vec<TimeEntry>,
+    series:  Vec<TimeEntry>,
+    /// The entry for the second currently being parsed.
+    current: Option<TimeEntry>,
 }
 
 impl TimeSeries {
     #[inline]
     pub fn new(meta: &Metadata) -> Self {
-        assert!(meta.log_end() > meta.log_start());
-
         TimeSeries {
-            series: Vec::with_capacity(((meta.log_end() - meta.log_start()) / 1000) as usize),
+            series:  Vec::with_capacity((meta.encounter().duration_ms() / 1000) as usize),
+            current: None,
         }
     }
 
@@ -364,28 +384,41 @@ impl TimeSeries {
     }
 
     #[inline]
-    pub fn parse<I: Iterator<Item=T>, T: Source>(&mut self, mut iter: I, meta: &Metadata) {
-        let mut entry = if let Some(event) = iter.next() {
-            let mut entry = TimeEntry::with_time(event.time() / 1000);
+    pub fn parse<I: Iterator<Item=T>, T: Source>(&mut self, iter: I, meta: &Metadata) {
+        for e in iter {
+            self.add_event(e, meta);
+        }
 
-            self.parse_item(&mut entry, event, meta);
+        self.finalize();
+    }
 
-            entry
-        }
-        else {
-            return;
-        };
+    /// Adds a single event to the series, events have to be added in time-order.
+    #[inline]
+    pub fn add_event<T: Source>(&mut self, e: T, meta: &Metadata) {
+        let time = e.time() / 1000;
 
-        for e in iter {
-            if entry.time != e.time() / 1000 {
-                if entry.has_data() {
-                    self.series.push(entry);
-                }
+        if self.current.as_ref().map(|c| c.time != time).unwrap_or(false) {
+            let entry = self.current.take().unwrap();
 
-                entry = TimeEntry::with_time(e.time() / 1000);
+            if entry.has_data() {
+                self.series.push(entry);
             }
+        }
+
+        let mut entry = self.current.take().unwrap_or_else(|| TimeEntry::with_time(time));
+
+        self.parse_item(&mut entry, e, meta);
 
-            self.parse_item(&mut entry, e, meta);
+        self.current = Some(entry);
+    }
+
+    /// Stores the last entry, should be called after the last event has been added.
+    #[inline]
+    pub fn finalize(&mut self) {
+        if let Some(entry) = self.current.take() {
+            if entry.has_data() {
+                self.series.push(entry);
+            }
         }
     }
 
@@ -12,8 +12,8 @@ use serde::ser::Serializer;
 
 use std::u32;
 use std::u64;
-use std::cmp;
 use std::fmt;
+use std::time::SystemTime;
 
 use event::raw::Agent as RawAgent;
 use event::raw::EvtcBuf;
@@ -24,9 +24,15 @@ use event::raw::CombatEventV1;
 
 use AgentId;
 use Boss;
+use Encounter;
+use EncounterEnd;
+use EncounterStart;
 use InstanceId;
 use Profession;
 use SpeciesId;
+use TargetSet;
+
+use time::Timestamp;
 
 /// A game agent present in the encounter
 #[derive(Debug, Clone)]
@@ -171,9 +177,10 @@ const EXTRA_BOSS_IDS: &'static [(SpeciesId, Profession)] = &[
 pub struct Metadata<'a> {
     buffer:    &'a EvtcBuf<'a>,
     agents:    Vec<Agent>,
-    start:     u32,
-    end:       u32,
-    /// Start of log, unix timestamp, server time
+    encounter: Encounter,
+    start:     Option<Timestamp>,
+    end:       Option<Timestamp>,
+    /// Start of log, relative milliseconds
     log_start: u64,
     log_end:   u64,
     lang:      Language,
@@ -184,8 +191,8 @@ pub struct Metadata<'a> {
 impl<'a> Metadata<'a> {
     pub fn new(buffer: &'a EvtcBuf) -> Self {
         let mut map   = FnvHashMap::<AgentId, AgentMetadata>::with_capacity_and_hasher(buffer.agents.len(), Default::default());
-        let mut start = u32::MAX;
-        let mut end   = 0;
+        let mut start = None;
+        let mut end   = None;
         let mut shard = 0;
         let mut build = 0;
         let mut lang  = Language::English;
@@ -193,12 +200,11 @@ impl<'a> Metadata<'a> {
         // Determine meta stuff
         for e in buffer.events.iter().filter_map(Event::into_meta) {
             match e.into_enum() {
-                // TODO: Save the extra values
-                MetaEventData::LogStart { server, .. } => start = server,
-                MetaEventData::LogEnd   { server, .. } => end   = server,
-                MetaEventData::Language(l)             => lang  = l,
-                MetaEventData::Gw2Build(b)             => build = b,
-                MetaEventData::ShardId(s)              => shard = s,
+                MetaEventData::LogStart { server, local, arcdps_id } => start = Some(Timestamp::new(e.time(), server, local, arcdps_id)),
+                MetaEventData::LogEnd   { server, local, arcdps_id } => end   = Some(Timestamp::new(e.time(), server, local, arcdps_id)),
+                MetaEventData::Language(l)                           => lang  = l,
+                MetaEventData::Gw2Build(b)                           => build = b,
+                MetaEventData::ShardId(s)                            => shard = s,
             }
         }
 
@@ -250,7 +256,7 @@ impl<'a> Metadata<'a> {
         }
 
         // TODO: Filter agents?
-        Metadata {
+        let mut meta = Metadata {
             buffer,
             agents: buffer.agents.iter().map(|agent| Agent {
                 inner: *agent,
@@ -263,7 +269,12 @@ impl<'a> Metadata<'a> {
             shard,
             log_start: buffer.events.first().as_ref().map(Event::time).unwrap_or(0),
             log_end:   buffer.events.last().as_ref().map(Event::time).unwrap_or(u64::MAX),
-        }
+            encounter: Encounter::from_span(0, 0),
+        };
+
+        meta.encounter = Encounter::new(&meta, EncounterStart::default(), EncounterEnd::default());
+
+        meta
     }
 
     pub fn agents(&self) -> &[Agent] {
@@ -279,17 +290,39 @@ impl<'a> Metadata<'a> {
                           .fold(false, |a, b| a || b))
     }
 
+    /// All agents which are a part of the given target set.
+    pub fn targets<'b>(&'b self, set: &'b TargetSet) -> impl Iterator<Item=&'b Agent> {
+        self.agents.iter().filter(move |a| set.contains(self, a))
+    }
+
     pub fn boss(&self) -> Boss {
         Boss::from_species_id(self.buffer.header.boss_id)
     }
 
-    /// Only returns the events which happened while the boss(es) were present in the fight,
-    /// does not contain gaps.
+    /// The time-span of the fight, using the default [EncounterStart] and [EncounterEnd], see
+    /// [Metadata::set_encounter] to use other boundaries.
+    #[inline]
+    pub fn encounter(&self) -> &Encounter {
+        &self.encounter
+    }
+
+    /// Replaces the encounter boundaries used by [Metadata::encounter_events] and the time-series.
+    #[inline]
+    pub fn set_encounter(&mut self, encounter: Encounter) {
+        self.encounter = encounter;
+    }
+
+    /// All the events of the log, including the ones outside of the encounter.
+    #[inline]
+    pub fn events(&self) -> &'a [CombatEventV1] {
+        self.buffer.events
+    }
+
+    /// Only returns the events which happened during the [Encounter], does not contain gaps.
     pub fn encounter_events(&'a self) -> impl 'a + Iterator<Item=&'a CombatEventV1> {
-        // TODO: Move to method
-        let (start, end) = self.bosses().fold((u64::MAX, 0), |(start, end), a| (cmp::min(start, a.first_aware()), cmp::max(end, a.last_aware())));
+        let encounter = self.encounter;
 
-        self.buffer.events.iter().filter(move |e| start <= e.time() && e.time() <= end)
+        self.buffer.events.iter().filter(move |e| encounter.contains(e.time()))
     }
 
     pub fn skills(&self) -> impl Iterator<Item=&Skill> {
@@ -312,15 +345,34 @@ impl<'a> Metadata<'a> {
     /// Start of log, unix timestamp, server time
     #[inline]
     pub fn log_start_time(&self) -> u32 {
-        self.start
+        self.start.map(|t| t.server()).unwrap_or(u32::MAX)
     }
 
     /// End of log, unix timestamp, server time
     #[inline]
     pub fn log_end_time(&self) -> u32 {
+        self.end.map(|t| t.server()).unwrap_or(0)
+    }
+
+    /// Wall-clock reference of the start of the log, if recorded
+    #[inline]
+    pub fn log_start_timestamp(&self) -> Option<Timestamp> {
+        self.start
+    }
+
+    /// Wall-clock reference of the end of the log, if recorded
+    #[inline]
+    pub fn log_end_timestamp(&self) -> Option<Timestamp> {
         self.end
     }
 
+    /// Converts a relative event time to a UTC instant using the start timestamp, or the end
+    /// timestamp if the log has no start, `None` if the log has neither.
+    #[inline]
+    pub fn to_utc(&self, time: u64) -> Option<SystemTime> {
+        self.start.or(self.end).map(|t| t.to_utc(time))
+    }
+
     /// Start of log, relative milliseconds
     #[inline]
     pub fn log_start(&self) -> u64 {
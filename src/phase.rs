@@ -0,0 +1,262 @@
+//! Boss invulnerability windows and splitting the encounter into phases.
+use Agent;
+use AgentId;
+use Encounter;
+
+use event::Buff;
+use event::BuffRemoval;
+use statistics::Sink;
+
+use std::cmp;
+
+/// Buffs which make the boss invulnerable.
+pub const INVULNERABILITY_BUFFS: &[u16] = &[
+    // Invulnerability
+    757,
+    // Determined, eg. Xera and Deimos
+    762,
+    34113,
+];
+
+/// The time-spans when a boss is invulnerable, derived from the [INVULNERABILITY_BUFFS].
+#[derive(Debug, Clone, Serialize)]
+pub struct Invulnerability {
+    #[serde(skip)]
+    agent:     AgentId,
+    #[serde(skip)]
+    encounter: Encounter,
+    /// Start of the current window, relative milliseconds
+    #[serde(skip)]
+    since:     Option<u64>,
+    /// Number of active invulnerability stacks
+    #[serde(skip)]
+    stacks:    u32,
+    /// Start and end, relative milliseconds
+    windows:   Vec<(u64, u64)>,
+}
+
+impl Default for Invulnerability {
+    #[inline]
+    fn default() -> Self {
+        Invulnerability {
+            agent:     AgentId::empty(),
+            encounter: Encounter::from_span(0, 0),
+            since:     None,
+            stacks:    0,
+            windows:   Vec::new(),
+        }
+    }
+}
+
+impl Invulnerability {
+    pub fn new(agent: &Agent, encounter: &Encounter) -> Self {
+        Invulnerability {
+            agent:     agent.id(),
+            encounter: *encounter,
+            since:     None,
+            stacks:    0,
+            windows:   Vec::new(),
+        }
+    }
+
+    /// Closes any open window at the end of the encounter.
+    pub fn finalize(&mut self) {
+        let end = self.encounter.end();
+
+        self.close(end);
+    }
+
+    /// The windows of invulnerability, limited to the encounter.
+    #[inline]
+    pub fn windows(&self) -> &[(u64, u64)] {
+        &self.windows
+    }
+
+    /// Milliseconds spent invulnerable.
+    #[inline]
+    pub fn invulnerable_time(&self) -> u64 {
+        self.windows.iter().map(|&(s, e)| e - s).sum()
+    }
+
+    fn close(&mut self, time: u64) {
+        if let Some(since) = self.since.take() {
+            let start = cmp::max(since, self.encounter.start());
+            let end   = cmp::min(time, self.encounter.end());
+
+            if start < end {
+                self.windows.push((start, end));
+            }
+        }
+    }
+}
+
+impl<T: Buff> Sink<T> for Invulnerability {
+    fn add_event(&mut self, e: T) {
+        if ! INVULNERABILITY_BUFFS.contains(&e.skill()) {
+            return;
+        }
+
+        // The source of buff removals is the agent losing the buff, the window stays open until
+        // all of the stacks are gone
+        if e.is_remove() {
+            if e.agent() == self.agent {
+                self.stacks = match e.removal() {
+                    BuffRemoval::All => 0,
+                    _                => self.stacks.saturating_sub(e.removed_stacks()),
+                };
+
+                if self.stacks == 0 {
+                    self.close(e.time());
+                }
+            }
+        }
+        else if e.target_agent() == self.agent {
+            self.stacks += 1;
+
+            if self.since.is_none() {
+                self.since = Some(e.time());
+            }
+        }
+    }
+}
+
+/// The windows where all of the bosses are invulnerable at the same time.
+pub fn combined_windows(invulns: &[Invulnerability]) -> Vec<(u64, u64)> {
+    let mut iter = invulns.iter();
+    let mut acc  = match iter.next() {
+        Some(i) => i.windows.clone(),
+        None    => return Vec::new(),
+    };
+
+    for i in iter {
+        let mut next = Vec::new();
+
+        for &(a_start, a_end) in &acc {
+            for &(b_start, b_end) in &i.windows {
+                let start = cmp::max(a_start, b_start);
+                let end   = cmp::min(a_end, b_end);
+
+                if start < end {
+                    next.push((start, end));
+                }
+            }
+        }
+
+        acc = next;
+    }
+
+    acc.sort();
+
+    acc
+}
+
+/// Splits the encounter into the phases between the invulnerability windows.
+pub fn phases(encounter: &Encounter, invulns: &[Invulnerability]) -> Vec<Encounter> {
+    let mut phases = Vec::new();
+    let mut start  = encounter.start();
+
+    for (s, e) in combined_windows(invulns) {
+        if start < s {
+            phases.push(Encounter::from_span(start, s));
+        }
+
+        start = cmp::max(start, e);
+    }
+
+    if start < encounter.end() {
+        phases.push(Encounter::from_span(start, encounter.end()));
+    }
+
+    phases
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use Metadata;
+
+    use event::Event;
+    use event::raw;
+    use event::raw::CombatBuffRemove;
+    use event::raw::CombatEventV1;
+    use event::raw::CombatStateChange;
+    use synthetic::Log;
+
+    /// The invulnerability windows of agents 1 and 2 during an encounter spanning 0 to 10000.
+    fn windows(events: &[CombatEventV1]) -> Vec<Invulnerability> {
+        let mut log = Log::new(15438);
+
+        log.npc(1, 15438, "A")
+           .npc(2, 15438, "B")
+           .event(CombatEventV1::new(0).src(1, 1).state_change(CombatStateChange::EnterCombat))
+           .event(CombatEventV1::new(0).src(2, 2).state_change(CombatStateChange::EnterCombat));
+
+        let mut events = events.to_vec();
+
+        events.sort_by_key(|e| e.time);
+
+        for e in events {
+            log.event(e);
+        }
+
+        let buffer    = log.to_bytes();
+        let evtc      = raw::transmute(&buffer);
+        let meta      = Metadata::new(&evtc);
+        let encounter = Encounter::from_span(0, 10000);
+
+        let mut invulns: Vec<_> = meta.agents().iter().map(|a| Invulnerability::new(a, &encounter)).collect();
+
+        for e in meta.events().iter().filter_map(Event::into_buff) {
+            for i in &mut invulns {
+                i.add_event(e.clone());
+            }
+        }
+
+        for i in &mut invulns {
+            i.finalize();
+        }
+
+        invulns
+    }
+
+    #[test]
+    fn split_phases() {
+        let invulns = windows(&[
+            CombatEventV1::new(2000).src(1, 1).dst(1, 1).buff_apply(757, 2000),
+            CombatEventV1::new(4000).src(1, 1).dst(1, 1).buff_remove(757, 0, CombatBuffRemove::All),
+            CombatEventV1::new(6000).src(1, 1).dst(1, 1).buff_apply(762, 1000),
+            CombatEventV1::new(7000).src(1, 1).dst(1, 1).buff_remove(762, 0, CombatBuffRemove::All),
+            CombatEventV1::new(3000).src(2, 2).dst(2, 2).buff_apply(757, 5000),
+            CombatEventV1::new(8000).src(2, 2).dst(2, 2).buff_remove(757, 0, CombatBuffRemove::All),
+        ]);
+        let encounter = Encounter::from_span(0, 10000);
+
+        assert_eq!(invulns[0].windows(), &[(2000, 4000), (6000, 7000)]);
+        assert_eq!(invulns[1].windows(), &[(3000, 8000)]);
+        assert_eq!(combined_windows(&invulns), vec![(3000, 4000), (6000, 7000)]);
+        assert_eq!(phases(&encounter, &invulns[..1]), vec![Encounter::from_span(0, 2000),
+                                                          Encounter::from_span(4000, 6000),
+                                                          Encounter::from_span(7000, 10000)]);
+    }
+
+    #[test]
+    fn overlapping_stacks() {
+        let invulns = windows(&[
+            // Two overlapping stacks, the window lasts until both are removed
+            CombatEventV1::new(1000).src(1, 1).dst(1, 1).buff_apply(757, 3000),
+            CombatEventV1::new(2000).src(1, 1).dst(1, 1).buff_apply(762, 5000),
+            CombatEventV1::new(4000).src(1, 1).dst(1, 1).buff_remove(757, 0, CombatBuffRemove::Single),
+            CombatEventV1::new(7000).src(1, 1).dst(1, 1).buff_remove(762, 0, CombatBuffRemove::Single),
+            // Removal of all of the stacks at once
+            CombatEventV1::new(8000).src(2, 2).dst(2, 2).buff_apply(757, 3000),
+            CombatEventV1::new(8500).src(2, 2).dst(2, 2).buff_apply(757, 3000),
+            CombatEventV1::new(9000).src(2, 2).dst(2, 2).buff_remove(757, 0, CombatBuffRemove::Single),
+            CombatEventV1::new(9500).src(2, 2).dst(2, 2).buff_remove(757, 0, CombatBuffRemove::All).removed_stacks(1),
+        ]);
+
+        assert_eq!(invulns[0].windows(), &[(1000, 7000)]);
+        assert_eq!(invulns[1].windows(), &[(8000, 9500)]);
+        assert_eq!(invulns[0].invulnerable_time(), 6000);
+    }
+}
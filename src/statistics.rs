@@ -1,25 +1,67 @@
 use event::Activation;
+use event::Buff;
+use event::BuffRemoval;
 use event::CastType;
 use event::Damage;
 use event::Event;
 use event::HitType;
 use event::Source;
 use event::StateChange;
+use event::Target;
 use event::raw::WEAPON_SWAP;
 
+use buff::BuffType;
+use buff::REMOVAL_TOLERANCE;
+use buff::table;
+
+use Agent;
+use AgentId;
+use Encounter;
+use Metadata;
+use Profession;
+use SpeciesId;
+
 use fnv::FnvHashMap;
+use fnv::FnvHashSet;
 
 use serde::ser::Serialize;
 use serde::ser::Serializer;
 
 use std::cmp;
+use std::hash::Hash;
 use std::i64;
+use std::marker::PhantomData;
+
+/// Derives `Sink<T>` and `FromIterator<T>` for a struct by forwarding the events to all fields,
+/// see the `evtc_derive` crate for the available attributes.
+pub use evtc_derive::Sink;
 
 /// A sink for statistics
 pub trait Sink<T>: Default {
     fn add_event(&mut self, e: T);
 }
 
+/// Derives `Merge` for a struct by merging all fields, fields marked with `#[merge(skip)]` are
+/// left as is.
+pub use evtc_derive::Merge;
+
+/// Statistics which can be combined, eg. to aggregate across players, groups or logs.
+pub trait Merge {
+    /// Merges the statistics from `other` into `self`.
+    fn merge(&mut self, other: &Self);
+}
+
+/// Merges all the statistics into a new value.
+pub fn merged<'a, S: 'a + Merge + Default, I: IntoIterator<Item=&'a S>>(iter: I) -> S {
+    let mut s = S::default();
+
+    for o in iter {
+        s.merge(o);
+    }
+
+    s
+}
+
 #[macro_export]
 macro_rules! sink_from_iter {
     ($t:ident, $u:ident) => {
@@ -38,7 +80,6 @@ impl<T: $u> ::std::iter::FromIterator<T> for $t {
 }
 
 sink_from_iter!(Hits, Damage);
-sink_from_iter!(Abilities, Damage);
 sink_from_iter!(ActivationLog, Source);
 
 #[derive(Debug, Copy, Clone)]
@@ -57,6 +98,13 @@ impl MinDamage {
     }
 }
 
+impl Merge for MinDamage {
+    #[inline]
+    fn merge(&mut self, other: &Self) {
+        self.add(other.0)
+    }
+}
+
 impl Serialize for MinDamage {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
       where S: Serializer {
@@ -100,6 +148,9 @@ pub struct Hits {
     missed:        u32,
     /// Number of hits absorbed by target
     absorbed:      u32,
+    /// Total damage absorbed by barrier
+    #[serde(rename="barrierDamage")]
+    barrier:       i64,
     /// Minimum hit damage
     #[serde(rename="minDamage")]
     min_damage:    MinDamage,
@@ -128,6 +179,8 @@ impl<T: Damage> Sink<T> for Hits {
             }
         }
 
+        self.barrier += e.barrier();
+
         if e.flanking() { self.flanking += 1; }
         if e.moving()   { self.moving   += 1; }
         if e.over90()   { self.scholar  += 1; }
@@ -145,34 +198,891 @@ impl<T: Damage> Sink<T> for Hits {
     }
 }
 
+impl Merge for Hits {
+    fn merge(&mut self, other: &Self) {
+        self.total_damage  += other.total_damage;
+        self.wasted_damage += other.wasted_damage;
+        self.hits          += other.hits;
+        self.criticals     += other.criticals;
+        self.flanking      += other.flanking;
+        self.scholar       += other.scholar;
+        self.glancing      += other.glancing;
+        self.moving        += other.moving;
+        self.interrupted   += other.interrupted;
+        self.blocked       += other.blocked;
+        self.evaded        += other.evaded;
+        self.missed        += other.missed;
+        self.absorbed      += other.absorbed;
+        self.barrier       += other.barrier;
+        self.max_damage     = cmp::max(self.max_damage, other.max_damage);
+
+        self.min_damage.merge(&other.min_damage);
+    }
+}
+
+impl Hits {
+    #[inline]
+    pub fn total_damage(&self) -> i64 {
+        self.total_damage
+    }
+
+    #[inline]
+    pub fn hits(&self) -> u32 {
+        self.hits
+    }
+
+    /// Total damage absorbed by barrier.
+    #[inline]
+    pub fn barrier(&self) -> i64 {
+        self.barrier
+    }
+
+    /// Number of hits which were not blocked, evaded, interrupted, absorbed or missed.
+    #[inline]
+    pub fn connected(&self) -> u32 {
+        self.hits - self.blocked - self.evaded - self.interrupted - self.absorbed - self.missed
+    }
+
+    /// Derived metrics, durations in milliseconds.
+    pub fn rates(&self, duration: u64, active: u64) -> Rates {
+        let connected = self.connected();
+
+        Rates {
+            dps:        per_second(self.total_damage, duration),
+            active_dps: per_second(self.total_damage, active),
+            critical:   percentage(self.criticals, connected),
+            flanking:   percentage(self.flanking, connected),
+            scholar:    percentage(self.scholar, connected),
+            glancing:   percentage(self.glancing, connected),
+            moving:     percentage(self.moving, connected),
+        }
+    }
+}
+
+fn per_second(damage: i64, ms: u64) -> f64 {
+    if ms == 0 { 0.0 } else { damage as f64 * 1000.0 / ms as f64 }
+}
+
+fn percentage(n: u32, total: u32) -> f64 {
+    if total == 0 { 0.0 } else { n as f64 * 100.0 / total as f64 }
+}
+
+/// Metrics derived from [Hits], rates are percentages of the hits which connected.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Serialize)]
+pub struct Rates {
+    /// Damage per second over the whole encounter
+    dps:        f64,
+    /// Damage per second over the time the agent was active, see [ActiveTime]
+    #[serde(rename="activeDps")]
+    active_dps: f64,
+    #[serde(rename="criticalRate")]
+    critical:   f64,
+    #[serde(rename="flankingRate")]
+    flanking:   f64,
+    #[serde(rename="scholarRate")]
+    scholar:    f64,
+    #[serde(rename="glancingRate")]
+    glancing:   f64,
+    #[serde(rename="movingRate")]
+    moving:     f64,
+}
+
+impl Rates {
+    #[inline]
+    pub fn dps(&self) -> f64 {
+        self.dps
+    }
+
+    #[inline]
+    pub fn active_dps(&self) -> f64 {
+        self.active_dps
+    }
+
+    #[inline]
+    pub fn critical_rate(&self) -> f64 {
+        self.critical
+    }
+
+    #[inline]
+    pub fn flanking_rate(&self) -> f64 {
+        self.flanking
+    }
+
+    #[inline]
+    pub fn scholar_rate(&self) -> f64 {
+        self.scholar
+    }
+}
+
+/// Time an agent was present and alive during the encounter, excluding time spent dead or
+/// disconnected.
+///
+/// Only state changes of the agent itself are used, events from other agents are ignored.
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveTime {
+    #[serde(skip)]
+    agent:          AgentId,
+    /// Relative milliseconds
+    #[serde(skip)]
+    start:          u64,
+    /// Relative milliseconds
+    #[serde(skip)]
+    end:            u64,
+    /// Start of the current dead or disconnected interval
+    #[serde(skip)]
+    inactive_since: Option<u64>,
+    /// Closed dead or disconnected intervals, relative milliseconds
+    #[serde(skip)]
+    windows:        Vec<(u64, u64)>,
+    /// Milliseconds spent dead or disconnected
+    inactive:       u64,
+    /// Milliseconds spent active
+    active:         u64,
+}
+
+impl Default for ActiveTime {
+    #[inline]
+    fn default() -> Self {
+        ActiveTime::from_span(AgentId::empty(), 0, 0)
+    }
+}
+
+impl ActiveTime {
+    /// Active time for the agent, limited to the encounter and the time the agent was observed.
+    pub fn new(agent: &Agent, encounter: &Encounter) -> Self {
+        ActiveTime::from_span(agent.id(),
+                              cmp::max(encounter.start(), agent.first_aware()),
+                              cmp::min(encounter.end(), agent.last_aware()))
+    }
+
+    fn from_span(agent: AgentId, start: u64, end: u64) -> Self {
+        let end = cmp::max(start, end);
+
+        ActiveTime {
+            agent,
+            start,
+            end,
+            inactive_since: None,
+            windows:        Vec::new(),
+            inactive:       0,
+            active:         end - start,
+        }
+    }
+
+    /// Milliseconds the agent was active.
+    #[inline]
+    pub fn active(&self) -> u64 {
+        self.active
+    }
+
+    /// Milliseconds the agent was dead or disconnected.
+    #[inline]
+    pub fn inactive(&self) -> u64 {
+        self.inactive
+    }
+
+    /// Milliseconds the agent was active between `start` and `end`, relative milliseconds.
+    pub fn active_within(&self, start: u64, end: u64) -> u64 {
+        let start = cmp::max(start, self.start);
+        let end   = cmp::min(end, self.end);
+
+        if start >= end {
+            return 0;
+        }
+
+        let inactive: u64 = self.windows.iter()
+                                .cloned()
+                                .chain(self.inactive_since.map(|t| (t, self.end)))
+                                .map(|(s, e)| cmp::min(e, end).saturating_sub(cmp::max(s, start)))
+                                .sum();
+
+        end - start - inactive
+    }
+
+    fn set_inactive(&mut self, since: Option<u64>, until: u64) {
+        let until = cmp::min(cmp::max(until, self.start), self.end);
+
+        if let Some(t) = self.inactive_since.take() {
+            let t = cmp::max(t, self.start);
+
+            if t < until {
+                self.inactive += until - t;
+                self.windows.push((t, until));
+            }
+        }
+
+        self.inactive_since = since;
+        self.active         = self.end - self.start - self.inactive
+                            - self.inactive_since.map(|t| self.end.saturating_sub(cmp::max(t, self.start))).unwrap_or(0);
+    }
+}
+
+impl<T: Source> Sink<T> for ActiveTime {
+    fn add_event(&mut self, e: T) {
+        if e.agent() != self.agent {
+            return;
+        }
+
+        match e.state_change() {
+            Some(StateChange::ChangeDead)
+            | Some(StateChange::Despawn) if self.inactive_since.is_none() => self.set_inactive(Some(e.time()), e.time()),
+            Some(StateChange::ChangeUp)
+            | Some(StateChange::Spawn)                                    => self.set_inactive(None, e.time()),
+            _                                                             => {},
+        }
+    }
+}
+
+/// Key for grouping statistics per target or source, NPCs and gadgets are grouped by their
+/// species.
+///
+/// NPCs and gadgets do not share species ids, so the kind of agent is a part of the key.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize)]
+#[serde(tag = "type", content = "id")]
+pub enum TargetKey {
+    #[serde(rename="agent")]
+    Agent(AgentId),
+    #[serde(rename="npc")]
+    Npc(SpeciesId),
+    #[serde(rename="gadget")]
+    Gadget(SpeciesId),
+}
+
+/// Statistics grouped per agent, the agent is picked from each event by `A`, see [TargetKey].
 #[derive(Debug, Clone)]
-pub struct Abilities {
-    abilities: FnvHashMap<u16, Hits>,
+pub struct PerAgent<A, S> {
+    /// Keys of the NPCs and gadgets present in the encounter.
+    keys:    FnvHashMap<AgentId, TargetKey>,
+    groups:  FnvHashMap<TargetKey, S>,
+    _agent:  PhantomData<A>,
 }
 
-impl Serialize for Abilities {
-    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-      where S: Serializer {
-        self.abilities.serialize(serializer)
+/// Statistics grouped per target agent, see [TargetKey].
+pub type PerTarget<S> = PerAgent<ByTarget, S>;
+
+/// Statistics grouped per source agent, see [TargetKey].
+pub type PerSource<S> = PerAgent<BySource, S>;
+
+/// Key-function picking the agent used to group the events of a [PerAgent].
+pub trait AgentKey: KeyFn<Key=AgentId> {
+    /// Name of the agent field in the serialized entries.
+    const FIELD: &'static str;
+}
+
+impl<A, S> Default for PerAgent<A, S> {
+    /// Creates a `PerAgent` without species information, all agents are grouped per agent.
+    #[inline]
+    fn default() -> Self {
+        PerAgent {
+            keys:    FnvHashMap::default(),
+            groups:  FnvHashMap::default(),
+            _agent:  PhantomData,
+        }
+    }
+}
+
+/// Keys of the NPCs and gadgets present in the encounter.
+fn species_keys(meta: &Metadata) -> FnvHashMap<AgentId, TargetKey> {
+    meta.agents()
+        .iter()
+        .filter_map(|a| match a.profession() {
+            Profession::NonPlayableCharacter(s) => Some((a.id(), TargetKey::Npc(s))),
+            Profession::Gadget(s)               => Some((a.id(), TargetKey::Gadget(s))),
+            _                                   => None,
+        })
+        .collect()
+}
+
+/// The key used for the given agent, agents with a species are grouped by species.
+#[inline]
+fn agent_key(keys: &FnvHashMap<AgentId, TargetKey>, agent: AgentId) -> TargetKey {
+    keys.get(&agent).cloned().unwrap_or(TargetKey::Agent(agent))
+}
+
+impl<A, S> PerAgent<A, S> {
+    /// Creates a `PerAgent` which groups NPCs and gadgets by their species.
+    pub fn new(meta: &Metadata) -> Self {
+        PerAgent {
+            keys:    species_keys(meta),
+            groups:  FnvHashMap::default(),
+            _agent:  PhantomData,
+        }
+    }
+
+    /// The key used for the given agent.
+    #[inline]
+    pub fn key(&self, agent: AgentId) -> TargetKey {
+        agent_key(&self.keys, agent)
+    }
+
+    #[inline]
+    pub fn get(&self, key: &TargetKey) -> Option<&S> {
+        self.groups.get(key)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=(&TargetKey, &S)> {
+        self.groups.iter()
+    }
+}
+
+impl<T, A: AgentKey + KeyOf<T>, S: Sink<T>> Sink<T> for PerAgent<A, S> {
+    #[inline]
+    fn add_event(&mut self, e: T) {
+        let key = self.key(A::key(&e));
+
+        self.groups.entry(key).or_default().add_event(e)
+    }
+}
+
+impl<A, S: Merge + Clone> Merge for PerAgent<A, S> {
+    fn merge(&mut self, other: &Self) {
+        for (&a, &k) in &other.keys {
+            self.keys.insert(a, k);
+        }
+
+        for (k, v) in &other.groups {
+            merge_entry(&mut self.groups, k, v);
+        }
+    }
+}
+
+/// Merges `value` into the entry for `key`, or inserts a copy if the entry is missing.
+fn merge_entry<K: Eq + Hash + Clone, S: Merge + Clone>(map: &mut FnvHashMap<K, S>, key: &K, value: &S) {
+    if let Some(s) = map.get_mut(key) {
+        s.merge(value);
+
+        return;
+    }
+
+    map.insert(key.clone(), value.clone());
+}
+
+impl<A: AgentKey, S: Serialize> Serialize for PerAgent<A, S> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+      where Ser: Serializer {
+        use serde::ser::SerializeMap;
+        use serde::ser::SerializeSeq;
+
+        struct Entry<'a, A, S: 'a> {
+            key:    &'a TargetKey,
+            stats:  &'a S,
+            _agent: PhantomData<A>,
+        }
+
+        impl<'a, A: AgentKey, S: Serialize> Serialize for Entry<'a, A, S> {
+            fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+              where Ser: Serializer {
+                let mut map = serializer.serialize_map(Some(2))?;
+
+                map.serialize_entry(A::FIELD, self.key)?;
+                map.serialize_entry("stats", self.stats)?;
+
+                map.end()
+            }
+        }
+
+        // Sorted to not depend on the iteration order of the map
+        let mut groups: Vec<_> = self.groups.iter().collect();
+
+        groups.sort_by_key(|&(k, _)| k);
+
+        let mut seq = serializer.serialize_seq(Some(groups.len()))?;
+
+        for (key, stats) in groups {
+            seq.serialize_element(&Entry::<A, S> { key, stats, _agent: PhantomData })?;
+        }
+
+        seq.end()
+    }
+}
+
+/// Hit statistics grouped per skill.
+pub type Abilities = Keyed<BySkill, Hits>;
+
+/// Key-function for [Keyed], implemented by zero-sized marker types.
+pub trait KeyFn {
+    type Key: Eq + Hash;
+}
+
+/// Extracts the key from an event of type `T`.
+pub trait KeyOf<T>: KeyFn {
+    fn key(e: &T) -> Self::Key;
+}
+
+/// Predicate for [Filtered], implemented by zero-sized marker types.
+pub trait Predicate<T> {
+    fn test(e: &T) -> bool;
+}
+
+/// Groups damage events by skill id.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BySkill;
+
+impl KeyFn for BySkill {
+    type Key = u16;
+}
+
+impl<T: Damage> KeyOf<T> for BySkill {
+    #[inline]
+    fn key(e: &T) -> u16 {
+        e.skill()
+    }
+}
+
+/// Groups events by source agent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BySource;
+
+impl KeyFn for BySource {
+    type Key = AgentId;
+}
+
+impl<T: Source> KeyOf<T> for BySource {
+    #[inline]
+    fn key(e: &T) -> AgentId {
+        e.agent()
+    }
+}
+
+impl AgentKey for BySource {
+    const FIELD: &'static str = "source";
+}
+
+/// Groups events by target agent.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByTarget;
+
+impl KeyFn for ByTarget {
+    type Key = AgentId;
+}
+
+impl<T: Target> KeyOf<T> for ByTarget {
+    #[inline]
+    fn key(e: &T) -> AgentId {
+        e.target_agent()
+    }
+}
+
+impl AgentKey for ByTarget {
+    const FIELD: &'static str = "target";
+}
+
+/// Groups damage events by hit type.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ByHitType;
+
+impl KeyFn for ByHitType {
+    type Key = HitType;
+}
+
+impl<T: Damage> KeyOf<T> for ByHitType {
+    #[inline]
+    fn key(e: &T) -> HitType {
+        e.hit_type()
+    }
+}
+
+/// Only condition damage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IsCondi;
+
+impl<T: Damage> Predicate<T> for IsCondi {
+    #[inline]
+    fn test(e: &T) -> bool {
+        e.hit_type() == HitType::Condi
+    }
+}
+
+/// Only physical damage, including hits which did not do any damage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IsPower;
+
+impl<T: Damage> Predicate<T> for IsPower {
+    #[inline]
+    fn test(e: &T) -> bool {
+        e.hit_type() != HitType::Condi
+    }
+}
+
+/// Only hits which did damage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IsNonZero;
+
+impl<T: Damage> Predicate<T> for IsNonZero {
+    #[inline]
+    fn test(e: &T) -> bool {
+        ! e.hit_type().is_zero()
+    }
+}
+
+/// A sink which groups events using the key-function `K`, each group is its own `S`.
+#[derive(Debug, Clone)]
+pub struct Keyed<K: KeyFn, S> {
+    groups: FnvHashMap<K::Key, S>,
+    _key:   PhantomData<K>,
+}
+
+impl<K: KeyFn, S> Default for Keyed<K, S> {
+    #[inline]
+    fn default() -> Self {
+        Keyed {
+            groups: FnvHashMap::default(),
+            _key:   PhantomData,
+        }
+    }
+}
+
+impl<K: KeyFn, S> Keyed<K, S> {
+    #[inline]
+    pub fn get(&self, key: &K::Key) -> Option<&S> {
+        self.groups.get(key)
+    }
+
+    #[inline]
+    pub fn iter(&self) -> impl Iterator<Item=(&K::Key, &S)> {
+        self.groups.iter()
+    }
+
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+}
+
+impl<T, K: KeyOf<T>, S: Sink<T>> Sink<T> for Keyed<K, S> {
+    #[inline]
+    fn add_event(&mut self, e: T) {
+        self.groups.entry(K::key(&e)).or_default().add_event(e)
+    }
+}
+
+impl<K: KeyFn, S: Merge + Clone> Merge for Keyed<K, S>
+  where K::Key: Clone {
+    fn merge(&mut self, other: &Self) {
+        for (k, v) in &other.groups {
+            merge_entry(&mut self.groups, k, v);
+        }
+    }
+}
+
+impl<K: KeyFn, S: Serialize> Serialize for Keyed<K, S>
+  where K::Key: Serialize {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+      where Ser: Serializer {
+        self.groups.serialize(serializer)
+    }
+}
+
+/// A sink which only forwards the events matching the predicate `P` to `S`.
+#[derive(Debug, Clone, Default)]
+pub struct Filtered<S, P> {
+    inner:      S,
+    _predicate: PhantomData<P>,
+}
+
+impl<S, P> Filtered<S, P> {
+    #[inline]
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<T, S: Sink<T>, P: Predicate<T> + Default> Sink<T> for Filtered<S, P> {
+    #[inline]
+    fn add_event(&mut self, e: T) {
+        if P::test(&e) {
+            self.inner.add_event(e)
+        }
+    }
+}
+
+impl<S: Merge, P> Merge for Filtered<S, P> {
+    #[inline]
+    fn merge(&mut self, other: &Self) {
+        self.inner.merge(&other.inner)
+    }
+}
+
+impl<S: Serialize, P> Serialize for Filtered<S, P> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+      where Ser: Serializer {
+        self.inner.serialize(serializer)
+    }
+}
+
+macro_rules! impl_sink_tuple {
+    ($($s:ident $i:tt),+) => {
+impl<T: Clone, $($s: Sink<T>),+> Sink<T> for ($($s,)+) {
+    #[inline]
+    fn add_event(&mut self, e: T) {
+        $(self.$i.add_event(e.clone());)+
+    }
+}
+
+impl<$($s: Merge),+> Merge for ($($s,)+) {
+    #[inline]
+    fn merge(&mut self, other: &Self) {
+        $(self.$i.merge(&other.$i);)+
+    }
+}
+    }
+}
+
+impl_sink_tuple!(A 0);
+impl_sink_tuple!(A 0, B 1);
+impl_sink_tuple!(A 0, B 1, C 2);
+impl_sink_tuple!(A 0, B 1, C 2, D 3);
+impl_sink_tuple!(A 0, B 1, C 2, D 3, E 4);
+impl_sink_tuple!(A 0, B 1, C 2, D 3, E 4, F 5);
+
+/// Width of the time-buckets of [Windowed], implemented by zero-sized marker types.
+pub trait Width {
+    /// Width of each bucket, milliseconds.
+    const MILLIS: u64;
+}
+
+/// One second buckets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Second;
+
+impl Width for Second {
+    const MILLIS: u64 = 1000;
+}
+
+/// Ten second buckets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TenSeconds;
+
+impl Width for TenSeconds {
+    const MILLIS: u64 = 10000;
+}
+
+/// One minute buckets.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Minute;
+
+impl Width for Minute {
+    const MILLIS: u64 = 60000;
+}
+
+/// A sink which splits the events into time-buckets of the width `W`, each bucket is its own `S`.
+///
+/// Events are assumed to arrive in time-order, empty buckets are not stored.
+#[derive(Debug, Clone)]
+pub struct Windowed<S, W: Width = Second> {
+    buckets: Vec<(u64, S)>,
+    _width:  PhantomData<W>,
+}
+
+impl<S, W: Width> Default for Windowed<S, W> {
+    #[inline]
+    fn default() -> Self {
+        Windowed {
+            buckets: Vec::new(),
+            _width:  PhantomData,
+        }
+    }
+}
+
+impl<S, W: Width> Windowed<S, W> {
+    /// Width of each bucket, milliseconds.
+    #[inline]
+    pub fn width(&self) -> u64 {
+        W::MILLIS
+    }
+
+    /// The buckets, with the start time of each bucket in milliseconds.
+    #[inline]
+    pub fn buckets(&self) -> &[(u64, S)] {
+        &self.buckets
+    }
+}
+
+impl<T: Event, S: Sink<T>, W: Width> Sink<T> for Windowed<S, W> {
+    #[inline]
+    fn add_event(&mut self, e: T) {
+        let start = e.time() - e.time() % W::MILLIS;
+
+        if self.buckets.last().map(|&(t, _)| t != start).unwrap_or(true) {
+            self.buckets.push((start, Default::default()));
+        }
+
+        self.buckets.last_mut().unwrap().1.add_event(e)
+    }
+}
+
+impl<S: Merge + Clone, W: Width> Merge for Windowed<S, W> {
+    /// Merges the buckets with the same start time.
+    fn merge(&mut self, other: &Self) {
+        let mut buckets = Vec::with_capacity(cmp::max(self.buckets.len(), other.buckets.len()));
+        let mut a       = self.buckets.drain(..).peekable();
+        let mut b       = other.buckets.iter().peekable();
+
+        loop {
+            let take_a = match (a.peek(), b.peek()) {
+                (Some(x), Some(y)) => if x.0 == y.0 {
+                    let (t, mut s) = a.next().unwrap();
+
+                    s.merge(&b.next().unwrap().1);
+                    buckets.push((t, s));
+
+                    continue;
+                } else { x.0 < y.0 },
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None)    => break,
+            };
+
+            if take_a {
+                buckets.push(a.next().unwrap());
+            }
+            else {
+                buckets.push(b.next().unwrap().clone());
+            }
+        }
+
+        drop(a);
+
+        self.buckets = buckets;
+    }
+}
+
+impl<S: Serialize, W: Width> Serialize for Windowed<S, W> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+      where Ser: Serializer {
+        use serde::ser::SerializeSeq;
+
+        #[derive(Serialize)]
+        struct Bucket<'a, S: 'a> {
+            time:  u64,
+            stats: &'a S,
+        }
+
+        let mut seq = serializer.serialize_seq(Some(self.buckets.len()))?;
+
+        for (time, stats) in &self.buckets {
+            seq.serialize_element(&Bucket { time: *time, stats })?;
+        }
+
+        seq.end()
     }
 }
 
-impl Default for Abilities {
+/// A sink which splits the events into phases, each phase is its own `S`.
+///
+/// Events outside of all of the phases are ignored.
+#[derive(Debug, Clone)]
+pub struct Phased<S> {
+    phases: Vec<(Encounter, S)>,
+}
+
+impl<S> Default for Phased<S> {
     #[inline]
     fn default() -> Self {
-        Self {
-            abilities: FnvHashMap::default(),
+        Phased {
+            phases: Vec::new(),
+        }
+    }
+}
+
+impl<S: Default> Phased<S> {
+    pub fn new(phases: &[Encounter]) -> Self {
+        Phased {
+            phases: phases.iter().map(|&p| (p, Default::default())).collect(),
         }
     }
 }
 
-impl<T: Damage> Sink<T> for Abilities {
+impl<S> Phased<S> {
+    #[inline]
+    pub fn phases(&self) -> &[(Encounter, S)] {
+        &self.phases
+    }
+}
+
+impl<T: Event, S: Sink<T>> Sink<T> for Phased<S> {
     #[inline]
     fn add_event(&mut self, e: T) {
-        self.abilities.entry(e.skill()).or_insert(Default::default()).add_event(e)
+        let time = e.time();
+
+        if let Some(p) = self.phases.iter_mut().find(|p| p.0.contains(time)) {
+            p.1.add_event(e)
+        }
     }
 }
 
+impl<S: Merge + Clone> Merge for Phased<S> {
+    /// Merges the statistics of each phase, both sinks must use the same phases.
+    fn merge(&mut self, other: &Self) {
+        if self.phases.is_empty() {
+            self.phases = other.phases.clone();
+
+            return;
+        }
+
+        assert!(other.phases.is_empty() || self.phases.iter().map(|p| p.0).eq(other.phases.iter().map(|p| p.0)),
+                "Phased sinks with different phases cannot be merged");
+
+        for (a, b) in self.phases.iter_mut().zip(other.phases.iter()) {
+            a.1.merge(&b.1);
+        }
+    }
+}
+
+impl<S: Serialize> Serialize for Phased<S> {
+    fn serialize<Ser>(&self, serializer: Ser) -> Result<Ser::Ok, Ser::Error>
+      where Ser: Serializer {
+        use serde::ser::SerializeSeq;
+
+        #[derive(Serialize)]
+        struct Phase<'a, S: 'a> {
+            phase: &'a Encounter,
+            stats: &'a S,
+        }
+
+        let mut seq = serializer.serialize_seq(Some(self.phases.len()))?;
+
+        for (phase, stats) in &self.phases {
+            seq.serialize_element(&Phase { phase, stats })?;
+        }
+
+        seq.end()
+    }
+}
+
+macro_rules! impl_from_iter {
+    ($t:ty, $($p:tt)*) => {
+impl<$($p)*> ::std::iter::FromIterator<T> for $t {
+    fn from_iter<I: IntoIterator<Item=T>>(iter: I) -> Self {
+        let mut s: Self = Default::default();
+
+        for e in iter {
+            s.add_event(e);
+        }
+
+        s
+    }
+}
+    }
+}
+
+impl_from_iter!(Keyed<K, S>, T, K: KeyOf<T>, S: Sink<T>);
+impl_from_iter!(Filtered<S, P>, T, S: Sink<T>, P: Predicate<T> + Default);
+impl_from_iter!(Windowed<S, W>, T: Event, S: Sink<T>, W: Width);
+impl_from_iter!(Phased<S>, T: Event, S: Sink<T>);
+impl_from_iter!(PerAgent<A, S>, T, A: AgentKey + KeyOf<T>, S: Sink<T>);
+
 #[derive(Clone, Debug, Default, Serialize)]
 pub struct ActivationEntry {
     time:      u64,
@@ -245,3 +1155,472 @@ impl<T: Source> Sink<T> for ActivationLog {
         }
     }
 }
+
+/// Merges the activations of the same agent, eg. from separate parts of a log, into a single
+/// log ordered by time. Merging the logs of different agents mixes their skill rotations.
+impl Merge for ActivationLog {
+    /// The latest of the pending activations is kept so that the merged log can be completed
+    /// by later events.
+    fn merge(&mut self, other: &Self) {
+        self.log.extend(other.log.iter().cloned());
+        self.log.sort_by_key(|a| a.time);
+
+        self.last = match (self.last, other.last) {
+            (Some(a), Some(b)) => Some(if b.0 > a.0 { b } else { a }),
+            (a, b)             => a.or(b),
+        };
+    }
+}
+
+/// Number of buff removals and the removed duration.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq, Serialize)]
+pub struct Removals {
+    count:    u32,
+    /// Milliseconds
+    duration: u64,
+}
+
+impl Merge for Removals {
+    #[inline]
+    fn merge(&mut self, other: &Self) {
+        self.count    += other.count;
+        self.duration += other.duration;
+    }
+}
+
+/// Boon strips and condition cleanses done and received by an agent, per buff.
+///
+/// Removals of conditions on allies count as cleanses, removals of boons on other agents count
+/// as strips. Any other removals, manual removals, eg. from leaving combat, stacks expiring by
+/// themselves and buffs missing from the buff table are ignored.
+///
+/// Removals caused by gadgets/minions target them rather than their master, see
+/// [Dispatcher::add_gadget](../dispatch/struct.Dispatcher.html#method.add_gadget).
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Dispels {
+    /// The agent and its gadgets/minions
+    #[serde(skip)]
+    agents:   FnvHashSet<AgentId>,
+    /// Players and their gadgets/minions
+    #[serde(skip)]
+    allies:   FnvHashSet<AgentId>,
+    /// Buffs removed from enemies by the agent
+    strips:   FnvHashMap<u16, Removals>,
+    /// Buffs removed from allies by the agent
+    cleanses: FnvHashMap<u16, Removals>,
+    /// Buffs removed from the agent by enemies
+    stripped: FnvHashMap<u16, Removals>,
+    /// Buffs removed from the agent by allies
+    cleansed: FnvHashMap<u16, Removals>,
+}
+
+impl Dispels {
+    pub fn new(meta: &Metadata, agent: &Agent) -> Self {
+        let players = meta.agents().iter().filter(|a| a.profession().is_player_character());
+
+        Dispels {
+            agents:   Some(agent).into_iter().chain(meta.agents_for_master(agent)).map(Agent::id).collect(),
+            allies:   players.flat_map(|p| Some(p).into_iter().chain(meta.agents_for_master(p))).map(Agent::id).collect(),
+            strips:   FnvHashMap::default(),
+            cleanses: FnvHashMap::default(),
+            stripped: FnvHashMap::default(),
+            cleansed: FnvHashMap::default(),
+        }
+    }
+
+    /// The removals the buff belongs to, `outgoing` if done by the agent and `ally` if the other
+    /// party is an ally.
+    fn removals(&mut self, outgoing: bool, ally: bool, kind: BuffType) -> Option<&mut FnvHashMap<u16, Removals>> {
+        match (outgoing, ally, kind) {
+            (true,  true,  BuffType::Condition) => Some(&mut self.cleanses),
+            (true,  false, BuffType::Boon)      => Some(&mut self.strips),
+            (false, true,  BuffType::Condition) => Some(&mut self.cleansed),
+            (false, false, BuffType::Boon)      => Some(&mut self.stripped),
+            _                                   => None,
+        }
+    }
+}
+
+impl<T: Buff> Sink<T> for Dispels {
+    fn add_event(&mut self, e: T) {
+        match e.removal() {
+            BuffRemoval::None | BuffRemoval::Manual => return,
+            _                                      => {},
+        }
+
+        // Removals of stacks which ran out, see buff::REMOVAL_TOLERANCE
+        if e.duration() <= REMOVAL_TOLERANCE {
+            return;
+        }
+
+        let kind = match table::META_MAP.get(e.skill()) {
+            Some(m) => m.kind,
+            None    => return,
+        };
+
+        // The source is the agent losing the buff and the target the one causing the removal
+        let removal = Removals { count: e.removed_stacks(), duration: e.duration() as u64 };
+
+        if self.agents.contains(&e.target_agent()) {
+            let ally = self.allies.contains(&e.agent());
+
+            if let Some(map) = self.removals(true, ally, kind) {
+                map.entry(e.skill()).or_default().merge(&removal);
+            }
+        }
+
+        if self.agents.contains(&e.agent()) {
+            let ally = self.allies.contains(&e.target_agent());
+
+            if let Some(map) = self.removals(false, ally, kind) {
+                map.entry(e.skill()).or_default().merge(&removal);
+            }
+        }
+    }
+}
+
+impl Merge for Dispels {
+    fn merge(&mut self, other: &Self) {
+        self.agents.extend(other.agents.iter().cloned());
+        self.allies.extend(other.allies.iter().cloned());
+
+        for (k, v) in &other.strips {
+            merge_entry(&mut self.strips, k, v);
+        }
+
+        for (k, v) in &other.cleanses {
+            merge_entry(&mut self.cleanses, k, v);
+        }
+
+        for (k, v) in &other.stripped {
+            merge_entry(&mut self.stripped, k, v);
+        }
+
+        for (k, v) in &other.cleansed {
+            merge_entry(&mut self.cleansed, k, v);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn merge_hits() {
+        let mut a = Hits { total_damage: 100, hits: 2, criticals: 1, max_damage: 60, ..Default::default() };
+        let b     = Hits { total_damage: 30,  hits: 3, criticals: 2, max_damage: 20, ..Default::default() };
+
+        a.min_damage.add(40);
+
+        a.merge(&b);
+
+        assert_eq!(a.total_damage, 130);
+        assert_eq!(a.hits, 5);
+        assert_eq!(a.criticals, 3);
+        assert_eq!(a.max_damage, 60);
+        // An empty minimum damage should not affect the merged minimum
+        assert_eq!(a.min_damage.0, 40);
+
+        let mut c = Hits::default();
+
+        c.min_damage.add(10);
+
+        a.merge(&c);
+
+        assert_eq!(a.min_damage.0, 10);
+    }
+
+    #[test]
+    fn active_time() {
+        use event::raw::CombatEventV1;
+        use event::raw::CombatStateChange;
+
+        let state  = |time, agent, change| CombatEventV1::new(time).src(agent, 0).state_change(change);
+        let mut t  = ActiveTime::from_span(AgentId::new(1), 1000, 11000);
+        let events = [
+            // Downed and rallied, still active
+            state(2000, 1, CombatStateChange::ChangeDown),
+            state(2500, 1, CombatStateChange::ChangeUp),
+            // Dead from 3000 until revived at 5000
+            state(3000, 1, CombatStateChange::ChangeDown),
+            state(3000, 1, CombatStateChange::ChangeDead),
+            state(5000, 1, CombatStateChange::ChangeUp),
+            // Other agents are ignored
+            state(6000, 2, CombatStateChange::ChangeDead),
+            // Disconnected at the end
+            state(10000, 1, CombatStateChange::Despawn),
+        ];
+        let mut active = Vec::new();
+
+        for e in events.iter().filter_map(Event::into_source) {
+            t.add_event(e);
+
+            active.push(t.active());
+        }
+
+        assert_eq!(active, [10000, 10000, 10000, 2000, 8000, 8000, 7000]);
+        assert_eq!(t.inactive(), 2000);
+
+        assert_eq!(t.active_within(0, 20000), 7000);
+        assert_eq!(t.active_within(2000, 4000), 1000);
+        assert_eq!(t.active_within(4000, 12000), 5000);
+        assert_eq!(t.active_within(3000, 5000), 0);
+
+        let h = Hits { total_damage: 7000, hits: 10, criticals: 5, blocked: 5, ..Default::default() };
+        let r = h.rates(10000, t.active());
+
+        assert_eq!(r.dps(), 700.0);
+        assert_eq!(r.active_dps(), 1000.0);
+        assert_eq!(r.critical_rate(), 100.0);
+    }
+
+    #[test]
+    fn merge_activations() {
+        use event::raw::CombatActivation;
+        use event::raw::CombatEventV1;
+
+        let cast = |time, skill, activation| CombatEventV1::new(time).src(1, 1).value(400).activation(skill, activation);
+
+        let mut a: ActivationLog = [
+            cast(1000, 100, CombatActivation::Normal),
+            cast(1400, 100, CombatActivation::Reset),
+        ].iter().filter_map(Event::into_source).collect();
+        let b: ActivationLog = [
+            cast(500,  200, CombatActivation::Normal),
+            cast(800,  200, CombatActivation::Cancel),
+            // Still casting at the end of the part
+            cast(2000, 300, CombatActivation::Quickness),
+        ].iter().filter_map(Event::into_source).collect();
+
+        a.merge(&b);
+
+        // The pending activation of the later part is completed after the merge
+        a.add_event((&cast(2300, 300, CombatActivation::Reset)).into_source().unwrap());
+
+        let json  = ::serde_json::to_value(&a).unwrap();
+        let times: Vec<_> = json.as_array().unwrap().iter().map(|e| (e["time"].as_u64().unwrap(), e["skill"].as_u64().unwrap())).collect();
+
+        assert_eq!(times, [(500, 200), (1000, 100), (2000, 300)]);
+        assert_eq!(json[0]["canceled"], true);
+        assert_eq!(json[2]["quickness"], true);
+    }
+
+    #[test]
+    fn combinators() {
+        use event::raw::CombatEventV1;
+        use event::raw::HitResult;
+
+        let hit    = |time, skill, damage| CombatEventV1::new(time).src(1, 0).dst(2, 0).skill(skill).damage(damage, 0, HitResult::Normal);
+        let events = [
+            hit(1000, 100, 100),
+            hit(1500, 200, 200),
+            hit(2500, 100, 300),
+            CombatEventV1::new(12000).src(1, 0).dst(2, 0).condi_damage(736, 50),
+        ];
+        let damage = || events.iter().filter_map(Event::into_damage);
+
+        let keyed: Abilities = damage().collect();
+
+        assert_eq!(keyed.len(), 3);
+        assert_eq!(keyed.get(&100).map(|h| (h.total_damage, h.hits)), Some((400, 2)));
+        assert_eq!(keyed.get(&736).map(|h| (h.total_damage, h.hits)), Some((50, 1)));
+
+        let condi: Filtered<Hits, IsCondi> = damage().collect();
+        let power: Filtered<Hits, IsPower> = damage().collect();
+
+        assert_eq!(condi.inner().total_damage, 50);
+        assert_eq!(power.inner().total_damage, 600);
+
+        // Every sink of a tuple receives each event
+        let mut tuple: (Hits, Filtered<Hits, IsCondi>) = Default::default();
+
+        for e in damage() {
+            tuple.add_event(e);
+        }
+
+        let other = tuple.clone();
+
+        tuple.merge(&other);
+
+        assert_eq!(tuple.0.total_damage, 1300);
+        assert_eq!(tuple.1.inner().total_damage, 100);
+
+        let windowed: Windowed<Hits> = damage().collect();
+
+        assert_eq!(windowed.width(), 1000);
+        assert_eq!(windowed.buckets().iter().map(|b| (b.0, b.1.total_damage)).collect::<Vec<_>>(), vec![(1000, 300), (2000, 300), (12000, 50)]);
+
+        // The width is kept when the windowed sink is created through Default
+        let nested: (Keyed<BySkill, Windowed<Hits, TenSeconds>>, Windowed<Hits, Minute>) = damage().fold(Default::default(), |mut s, e| { s.add_event(e); s });
+        let skill  = nested.0.get(&100).unwrap();
+
+        assert_eq!(skill.width(), 10000);
+        assert_eq!(skill.buckets().iter().map(|b| (b.0, b.1.total_damage)).collect::<Vec<_>>(), vec![(0, 400)]);
+        assert_eq!(nested.1.width(), 60000);
+        assert_eq!(nested.1.buckets().len(), 1);
+
+        let mut early: Windowed<Hits> = damage().take(2).collect();
+        let late:      Windowed<Hits> = damage().skip(1).collect();
+
+        early.merge(&late);
+
+        assert_eq!(early.buckets().iter().map(|b| (b.0, b.1.hits)).collect::<Vec<_>>(), vec![(1000, 3), (2000, 1), (12000, 1)]);
+    }
+
+    #[test]
+    fn incoming_per_source() {
+        use event::raw::CombatEventV1;
+        use event::raw::HitResult;
+
+        let hit    = |src, damage, barrier, result| CombatEventV1::new(1000).src(src, 0).dst(2, 0).skill(100).damage(damage, barrier, result);
+        let events = [
+            hit(1, 1000, 300, HitResult::Normal),
+            hit(1, 0, 0, HitResult::Absorb),
+            hit(1, 0, 0, HitResult::Block),
+            hit(1, 0, 0, HitResult::Evade),
+            hit(1, 0, 0, HitResult::Blind),
+            hit(5, 500, 0, HitResult::Normal),
+        ];
+
+        let incoming: PerSource<Abilities> = events.iter().filter_map(Event::into_damage).collect();
+        let boss = incoming.get(&TargetKey::Agent(AgentId::new(1))).and_then(|a| a.get(&100)).unwrap();
+        let add  = incoming.get(&TargetKey::Agent(AgentId::new(5))).and_then(|a| a.get(&100)).unwrap();
+
+        assert_eq!(boss.hits, 5);
+        assert_eq!(boss.total_damage, 1000);
+        assert_eq!(boss.barrier, 300);
+        assert_eq!((boss.absorbed, boss.blocked, boss.evaded, boss.missed), (1, 1, 1, 1));
+        assert_eq!(add.total_damage, 500);
+        assert_eq!(add.barrier, 0);
+
+        let json = ::serde_json::to_string(&incoming).unwrap();
+
+        assert!(json.contains(r#"{"source":{"type":"agent","id":1},"stats":{"100":{"totalDamage":1000,"#));
+    }
+
+    #[test]
+    fn per_target_kinds() {
+        use event::raw;
+        use event::raw::CombatEventV1;
+        use event::raw::HitResult;
+        use synthetic::Log;
+
+        let mut log = Log::new(15438);
+
+        // An NPC and a gadget sharing a species id, and two NPCs of the same species
+        log.npc(1, 15438, "Boss")
+           .npc(2, 4000, "Add")
+           .npc(3, 4000, "Add")
+           .gadget(4, 4000, "Pylon")
+           .player(5, 1, "A", 1);
+
+        for (t, dst) in [(1000, 4), (2000, 3), (3000, 2), (4000, 1), (5000, 6)].iter().cloned() {
+            log.event(CombatEventV1::new(t).src(5, 5).dst(dst, 0).skill(100).damage(100, 0, HitResult::Normal));
+        }
+
+        let buffer = log.to_bytes();
+        let evtc   = raw::transmute(&buffer);
+        let meta   = Metadata::new(&evtc);
+
+        let mut hits: PerTarget<Hits> = PerTarget::new(&meta);
+
+        for e in meta.events().iter().filter_map(Event::into_damage) {
+            hits.add_event(e);
+        }
+
+        assert_eq!(hits.get(&TargetKey::Npc(SpeciesId::new(4000))).map(|h| h.hits), Some(2));
+        assert_eq!(hits.get(&TargetKey::Gadget(SpeciesId::new(4000))).map(|h| h.hits), Some(1));
+        assert_eq!(hits.get(&TargetKey::Npc(SpeciesId::new(15438))).map(|h| h.hits), Some(1));
+        // Agents without a species are kept per agent
+        assert_eq!(hits.get(&TargetKey::Agent(AgentId::new(6))).map(|h| h.hits), Some(1));
+
+        let json = ::serde_json::to_value(&hits).unwrap();
+        let keys: Vec<_> = json.as_array().unwrap().iter().map(|e| (e["target"]["type"].as_str().unwrap().to_owned(), e["target"]["id"].as_u64().unwrap())).collect();
+
+        // Serialized in key order
+        assert_eq!(keys, [("agent".to_owned(), 6), ("npc".to_owned(), 4000), ("npc".to_owned(), 15438), ("gadget".to_owned(), 4000)]);
+    }
+
+    #[test]
+    fn dispels() {
+        use dispatch::Dispatcher;
+        use dispatch::Route;
+        use event::raw;
+        use event::raw::CombatBuffRemove::*;
+        use event::raw::CombatEventV1;
+        use event::raw::CombatStateChange;
+        use synthetic::Log;
+
+        let remove = |t, src: (u64, u16), dst: (u64, u16), skill, r| CombatEventV1::new(t).src(src.0, src.1).dst(dst.0, dst.1).buff_remove(skill, 1000, r);
+        let boss   = (1, 1);
+        let a      = (2, 2);
+        let b      = (3, 3);
+        let minion = (4, 4);
+        let mut log = Log::new(15438);
+
+        log.npc(boss.0, 15438, "Boss")
+           .player(a.0, 1, "A", 1)
+           .player(b.0, 2, "B", 1)
+           .npc(minion.0, 6001, "Minion")
+           .event(CombatEventV1::new(1000).src(boss.0, boss.1).state_change(CombatStateChange::EnterCombat))
+           .event(CombatEventV1::new(1000).src(a.0, a.1).state_change(CombatStateChange::EnterCombat))
+           .event(CombatEventV1::new(1000).src(b.0, b.1).state_change(CombatStateChange::EnterCombat))
+           .event(CombatEventV1::new(1100).src(minion.0, minion.1).master(a.1).dst(boss.0, boss.1).skill(6000).damage(100, 0, raw::HitResult::Normal))
+           // A strips protection off the boss, the minion strips quickness
+           .event(remove(2000, boss, a, 717, Single))
+           .event(remove(2000, boss, minion, 1187, All).removed_stacks(3))
+           // A cleanses bleeding off B, the minion cleanses vulnerability
+           .event(remove(3000, b, a, 736, Single))
+           .event(remove(3000, b, minion, 738, Single))
+           // The boss strips might off A, B cleanses poison off A
+           .event(remove(4000, a, boss, 740, Single))
+           .event(remove(4000, a, b, 723, All))
+           // Ignored: boons removed from allies, conditions removed from enemies, buffs not in
+           // the table and manual removals
+           .event(remove(5000, b, a, 740, Single))
+           .event(remove(5000, boss, a, 736, Single))
+           .event(remove(5000, boss, a, 12345, Single))
+           .event(remove(5000, boss, a, 1187, Manual))
+           // Ignored: stacks running out
+           .event(remove(6000, boss, a, 717, Single).value(0))
+           .event(remove(6000, a, boss, 740, All).value(20).removed_stacks(2))
+           .event(CombatEventV1::new(9000).src(boss.0, boss.1).state_change(CombatStateChange::ChangeDead));
+
+        let buffer = log.to_bytes();
+        let evtc   = raw::transmute(&buffer);
+        let meta   = Metadata::new(&evtc);
+        let agents = meta.agents();
+        let mut da = Dispels::new(&meta, &agents[1]);
+        let mut db = Dispels::new(&meta, &agents[2]);
+
+        assert_eq!(meta.agents_for_master(&agents[1]).next(), Some(&agents[3]));
+
+        {
+            let mut dispatcher: Dispatcher<&CombatEventV1> = Dispatcher::new();
+
+            dispatcher.add_agent(&agents[1], Route::Related, |e| if let Some(b) = e.into_buff() { da.add_event(b) });
+            dispatcher.add_agent(&agents[2], Route::Related, |e| if let Some(b) = e.into_buff() { db.add_event(b) });
+            dispatcher.add_gadget(&agents[1], &agents[3]);
+
+            dispatcher.run(meta.encounter_events());
+        }
+
+        let one  = Some(&Removals { count: 1, duration: 1000 });
+        let keys = |m: &FnvHashMap<u16, Removals>| { let mut k: Vec<_> = m.keys().cloned().collect(); k.sort(); k };
+
+        assert_eq!(keys(&da.strips), [717, 1187]);
+        assert_eq!(da.strips.get(&717), one);
+        assert_eq!(da.strips.get(&1187), Some(&Removals { count: 3, duration: 1000 }));
+        assert_eq!(da.stripped.get(&740), one);
+        assert_eq!(keys(&da.cleanses), [736, 738]);
+        assert_eq!(keys(&da.stripped), [740]);
+        assert_eq!(keys(&da.cleansed), [723]);
+
+        assert!(db.strips.is_empty());
+        assert_eq!(keys(&db.cleanses), [723]);
+        assert!(db.stripped.is_empty());
+        assert_eq!(keys(&db.cleansed), [736, 738]);
+        assert_eq!(db.cleansed.get(&738), one);
+    }
+}
@@ -0,0 +1,406 @@
+//! Generation of synthetic EVTC-logs for tests and benchmarks.
+//!
+//! The logs are written in the same format as arcdps writes them and can be read using
+//! [raw::transmute](../raw/fn.transmute.html):
+//!
+//! ```
+//! use evtc::Metadata;
+//! use evtc::raw;
+//! use evtc::synthetic;
+//!
+//! let buffer = synthetic::squad(10, 60000);
+//! let evtc   = raw::transmute(&buffer);
+//! let meta   = Metadata::new(&evtc);
+//!
+//! assert_eq!(meta.agents().iter().filter(|a| a.profession().is_player_character()).count(), 10);
+//! ```
+//!
+//! Custom logs are built using [Log] and [Event].
+use event::raw::CombatActivation;
+use event::raw::CombatBuffRemove;
+use event::raw::CombatEventV1;
+use event::raw::CombatStateChange;
+use event::raw::HitResult;
+use event::raw::IFF;
+
+use std::mem;
+use std::slice;
+
+/// Species id of the boss of [squad], Vale Guardian.
+pub const BOSS_SPECIES: u16 = 15438;
+/// Species id of the minions of [squad].
+pub const MINION_SPECIES: u16 = 6001;
+/// Agent id of the boss of [squad].
+pub const BOSS: u64 = 1;
+/// Instance id of the boss of [squad].
+pub const BOSS_INSTANCE: u16 = 1;
+/// Start of the log of [squad], relative milliseconds.
+pub const START: u64 = 1000;
+
+const VERSION: &[u8; 12] = b"EVTC20180801";
+
+/// Agent id of the n:th player of [squad].
+pub fn player(n: usize) -> u64 {
+    100 + n as u64
+}
+
+/// Instance id of the n:th player of [squad].
+pub fn player_instance(n: usize) -> u16 {
+    10 + n as u16
+}
+
+/// Agent id of the minion of the n:th player of [squad].
+pub fn minion(n: usize) -> u64 {
+    200 + n as u64
+}
+
+/// Instance id of the minion of the n:th player of [squad].
+pub fn minion_instance(n: usize) -> u16 {
+    40 + n as u16
+}
+
+/// Builder for the events of a synthetic [Log], every field which is not set is zero.
+///
+/// ```
+/// use evtc::event::raw::CombatStateChange;
+/// use evtc::synthetic::Event;
+/// use evtc::synthetic::Log;
+///
+/// let mut log = Log::new(15438);
+///
+/// log.npc(1, 15438, "Vale Guardian")
+///    .player(2, 1, "Player", 1)
+///    .event(Event::new(1000).src(1, 1).state_change(CombatStateChange::EnterCombat))
+///    .event(Event::new(2000).src(2, 2).dst(1, 1).buff_apply(736, 5000));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Event(CombatEventV1);
+
+impl Event {
+    /// An event at `time`, relative milliseconds.
+    pub fn new(time: u64) -> Self {
+        Event(CombatEventV1 {
+            time,
+            src_agent:         0,
+            dst_agent:         0,
+            value:             0,
+            buff_dmg:          0,
+            overstack:         0,
+            skill_id:          0,
+            src_instid:        0,
+            dst_instid:        0,
+            src_master_instid: 0,
+            _pad8:             0,
+            _pad1:             0,
+            iff:               IFF::Friend,
+            buff:              0,
+            result:            0,
+            is_activation:     CombatActivation::None,
+            is_buffremove:     CombatBuffRemove::None,
+            is_src_ninety:     0,
+            is_dst_fifty:      0,
+            is_src_moving:     0,
+            is_statechange:    CombatStateChange::None,
+            is_flanking:       0,
+            is_shields:        0,
+            is_offcycle:       0,
+            _pad2:             0,
+        })
+    }
+
+    /// The agent causing the event.
+    pub fn src(mut self, agent: u64, instid: u16) -> Self {
+        self.0.src_agent  = agent;
+        self.0.src_instid = instid;
+
+        self
+    }
+
+    /// The agent affected by the event.
+    pub fn dst(mut self, agent: u64, instid: u16) -> Self {
+        self.0.dst_agent  = agent;
+        self.0.dst_instid = instid;
+
+        self
+    }
+
+    /// Instance id of the master if the source is a minion or gadget.
+    pub fn master(mut self, instid: u16) -> Self {
+        self.0.src_master_instid = instid;
+
+        self
+    }
+
+    pub fn skill(mut self, skill: u16) -> Self {
+        self.0.skill_id = skill;
+
+        self
+    }
+
+    pub fn value(mut self, value: i32) -> Self {
+        self.0.value = value;
+
+        self
+    }
+
+    /// Direct damage, `barrier` is the part of the damage absorbed by barrier.
+    pub fn damage(mut self, damage: i32, barrier: u16, result: HitResult) -> Self {
+        self.0.value      = damage;
+        self.0.overstack  = barrier;
+        self.0.is_shields = if barrier > 0 { 1 } else { 0 };
+        self.0.result     = result as u8;
+
+        self
+    }
+
+    /// Condition damage tick of the buff.
+    pub fn condi_damage(mut self, skill: u16, damage: i32) -> Self {
+        self.0.buff     = 1;
+        self.0.skill_id = skill;
+        self.0.buff_dmg = damage;
+
+        self
+    }
+
+    /// Buff application with the duration in milliseconds.
+    pub fn buff_apply(mut self, skill: u16, duration: i32) -> Self {
+        self.0.buff     = 1;
+        self.0.skill_id = skill;
+        self.0.value    = duration;
+
+        self
+    }
+
+    /// Buff removal, `src` has the buff removed and `dst` caused it.
+    pub fn buff_remove(mut self, skill: u16, duration: i32, removal: CombatBuffRemove) -> Self {
+        self.0.buff          = 1;
+        self.0.skill_id      = skill;
+        self.0.value         = duration;
+        self.0.is_buffremove = removal;
+
+        self
+    }
+
+    /// Number of stacks removed by a removal of all stacks.
+    pub fn removed_stacks(mut self, stacks: u8) -> Self {
+        self.0.result = stacks;
+
+        self
+    }
+
+    pub fn activation(mut self, skill: u16, activation: CombatActivation) -> Self {
+        self.0.skill_id      = skill;
+        self.0.is_activation = activation;
+
+        self
+    }
+
+    pub fn state_change(mut self, state: CombatStateChange) -> Self {
+        self.0.is_statechange = state;
+
+        self
+    }
+}
+
+impl From<CombatEventV1> for Event {
+    #[inline]
+    fn from(e: CombatEventV1) -> Self {
+        Event(e)
+    }
+}
+
+impl From<Event> for CombatEventV1 {
+    #[inline]
+    fn from(e: Event) -> Self {
+        e.0
+    }
+}
+
+/// Builder for a synthetic log.
+#[derive(Debug, Clone)]
+pub struct Log {
+    boss:   u16,
+    agents: Vec<u8>,
+    count:  u32,
+    events: Vec<CombatEventV1>,
+}
+
+impl Log {
+    /// An empty log of an encounter with the boss of the given species id.
+    pub fn new(boss: u16) -> Self {
+        Log {
+            boss,
+            agents: Vec::new(),
+            count:  0,
+            events: Vec::new(),
+        }
+    }
+
+    /// Adds a player character, `profession` is the core profession id (1-9).
+    pub fn player(&mut self, id: u64, profession: u32, name: &str, subgroup: u8) -> &mut Self {
+        let account = format!(":{}.1234", name);
+
+        self.agent(id, profession, 0, &[name, &account, &subgroup.to_string()])
+    }
+
+    /// Adds a non-player character of the given species.
+    pub fn npc(&mut self, id: u64, species: u16, name: &str) -> &mut Self {
+        self.agent(id, species as u32, 0xFFFFFFFF, &[name, "", ""])
+    }
+
+    /// Adds a gadget of the given species.
+    pub fn gadget(&mut self, id: u64, species: u16, name: &str) -> &mut Self {
+        self.agent(id, 0xFFFF0000 | species as u32, 0xFFFFFFFF, &[name, "", ""])
+    }
+
+    /// Adds an event, events have to be added in time-order.
+    pub fn event<E: Into<CombatEventV1>>(&mut self, event: E) -> &mut Self {
+        self.events.push(event.into());
+
+        self
+    }
+
+    fn agent(&mut self, id: u64, profession: u32, is_elite: u32, names: &[&str]) -> &mut Self {
+        let mut name = [0u8; 68];
+        let mut i    = 0;
+
+        for n in names {
+            name[i..i + n.len()].copy_from_slice(n.as_bytes());
+
+            i += n.len() + 1;
+        }
+
+        push_u64(&mut self.agents, id);
+        push_u32(&mut self.agents, profession);
+        push_u32(&mut self.agents, is_elite);
+
+        // Toughness, concentration, healing, padding, condition damage, padding
+        for _ in 0..6 {
+            self.agents.extend_from_slice(&[0, 0]);
+        }
+
+        self.agents.extend_from_slice(&name);
+        self.count += 1;
+
+        self
+    }
+
+    /// The log in the binary EVTC-format.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(20 + self.agents.len() + 4 + self.events.len() * mem::size_of::<CombatEventV1>());
+
+        buf.extend_from_slice(VERSION);
+        buf.push(0);
+        buf.extend_from_slice(&[self.boss as u8, (self.boss >> 8) as u8]);
+        buf.push(0);
+        push_u32(&mut buf, self.count);
+        buf.extend_from_slice(&self.agents);
+        // No skills
+        push_u32(&mut buf, 0);
+
+        let events = unsafe {
+            slice::from_raw_parts(self.events.as_ptr() as *const u8, self.events.len() * mem::size_of::<CombatEventV1>())
+        };
+
+        buf.extend_from_slice(events);
+
+        buf
+    }
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    for i in 0..4 {
+        buf.push((v >> (i * 8)) as u8);
+    }
+}
+
+fn push_u64(buf: &mut Vec<u8>, v: u64) {
+    push_u32(buf, v as u32);
+    push_u32(buf, (v >> 32) as u32);
+}
+
+/// Deterministic pseudo-random numbers, a linear congruential generator.
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self, n: u32) -> u32 {
+        self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+
+        ((self.0 >> 33) % n as u64) as u32
+    }
+}
+
+/// A log of `players` players, each with a minion, fighting [BOSS] for `duration` milliseconds.
+///
+/// Every 10 ms one of the players or the boss acts; direct damage by players and minions, boss
+/// hits which are blocked, evaded, missed or absorbed, Might and Quickness on players, Bleeding
+/// on the boss, Vulnerability on players and strips and cleanses of those.
+pub fn squad(players: usize, duration: u64) -> Vec<u8> {
+    let mut log = Log::new(BOSS_SPECIES);
+    let mut rng = Lcg(players as u64 ^ duration);
+    let end     = START + duration;
+
+    log.npc(BOSS, BOSS_SPECIES, "Vale Guardian");
+
+    for p in 0..players {
+        log.player(player(p), 1 + (p % 9) as u32, &format!("Player {}", p), 1 + (p / 5) as u8);
+        log.npc(minion(p), MINION_SPECIES, "Minion");
+    }
+
+    log.event(Event::new(START).src(0x637261, 0).value(1533081600).state_change(CombatStateChange::LogStart));
+    log.event(Event::new(START).src(BOSS, BOSS_INSTANCE).state_change(CombatStateChange::EnterCombat));
+
+    for p in 0..players {
+        log.event(Event::new(START).src(player(p), player_instance(p)).dst(1 + (p / 5) as u64, 0).state_change(CombatStateChange::EnterCombat));
+    }
+
+    let mut time = START + 10;
+
+    while time < end {
+        let p      = rng.next(players as u32) as usize;
+        let other  = rng.next(players as u32) as usize;
+        let src    = (player(p), player_instance(p));
+        let target = (player(other), player_instance(other));
+        let boss   = (BOSS, BOSS_INSTANCE);
+
+        let e = match rng.next(10) {
+            0..=2     => Event::new(time).src(src.0, src.1).dst(boss.0, boss.1)
+                                        .skill(5000 + p as u16)
+                                        .damage(500 + rng.next(2000) as i32, 0, if rng.next(3) == 0 { HitResult::Crit } else { HitResult::Normal }),
+            3         => Event::new(time).src(minion(p), minion_instance(p)).master(player_instance(p)).dst(boss.0, boss.1)
+                                        .skill(6000)
+                                        .damage(200 + rng.next(500) as i32, 0, HitResult::Normal),
+            4         => {
+                let result  = [HitResult::Normal, HitResult::Block, HitResult::Evade, HitResult::Absorb, HitResult::Blind][rng.next(5) as usize];
+                let barrier = if result == HitResult::Normal && rng.next(2) == 0 { 100 + rng.next(400) as u16 } else { 0 };
+                let damage  = if result == HitResult::Normal { 1000 + rng.next(4000) as i32 } else { 0 };
+
+                Event::new(time).src(boss.0, boss.1).dst(target.0, target.1).skill(31860).damage(damage, barrier, result)
+            },
+            5         => Event::new(time).src(src.0, src.1).dst(target.0, target.1).buff_apply(740, 8000),
+            6         => if rng.next(2) == 0 {
+                Event::new(time).src(src.0, src.1).dst(boss.0, boss.1).buff_apply(736, 5000)
+            } else {
+                Event::new(time).src(boss.0, boss.1).dst(target.0, target.1).buff_apply(738, 6000)
+            },
+            7         => Event::new(time).src(src.0, src.1).dst(target.0, target.1).buff_apply(1187, 2000),
+            8         => if rng.next(2) == 0 {
+                // Boss strips a stack of might
+                Event::new(time).src(target.0, target.1).dst(boss.0, boss.1).buff_remove(740, 4000, CombatBuffRemove::Single)
+            } else {
+                // Minion cleanses vulnerability
+                Event::new(time).src(target.0, target.1).dst(minion(p), minion_instance(p)).buff_remove(738, 6000, CombatBuffRemove::All)
+            },
+            _         => Event::new(time).src(src.0, src.1).activation(5000 + p as u16, CombatActivation::Normal),
+        };
+
+        log.event(e);
+
+        time += 10;
+    }
+
+    log.event(Event::new(end).src(BOSS, BOSS_INSTANCE).state_change(CombatStateChange::ChangeDead));
+    log.event(Event::new(end).src(0x637261, 0).value(1533081600 + (duration / 1000) as i32).state_change(CombatStateChange::LogEnd));
+
+    log.to_bytes()
+}
@@ -0,0 +1,53 @@
+use Agent;
+use AgentId;
+use Metadata;
+use SpeciesId;
+
+use std::fmt;
+
+/// A set of agents which are considered targets when splitting damage into target- and
+/// cleave-damage.
+#[derive(Default)]
+pub enum TargetSet {
+    /// The boss(es) of the encounter, see [Metadata::bosses].
+    #[default]
+    Bosses,
+    /// All agents with any of the listed species ids, includes both NPCs and gadgets.
+    Species(Vec<SpeciesId>),
+    /// The agents with the listed agent ids.
+    Agents(Vec<AgentId>),
+    /// All agents matching the predicate.
+    Predicate(Box<dyn Fn(&Agent) -> bool + Send + Sync>),
+    /// All agents matching any of the contained sets.
+    Any(Vec<TargetSet>),
+}
+
+impl fmt::Debug for TargetSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TargetSet::Bosses       => write!(f, "Bosses"),
+            TargetSet::Species(s)   => write!(f, "Species({:?})", s),
+            TargetSet::Agents(a)    => write!(f, "Agents({:?})", a),
+            TargetSet::Predicate(_) => write!(f, "Predicate(..)"),
+            TargetSet::Any(s)       => write!(f, "Any({:?})", s),
+        }
+    }
+}
+
+impl TargetSet {
+    /// Creates a target set from a predicate.
+    pub fn predicate<F: Fn(&Agent) -> bool + Send + Sync + 'static>(f: F) -> Self {
+        TargetSet::Predicate(Box::new(f))
+    }
+
+    /// True if the agent is a part of this target set.
+    pub fn contains(&self, meta: &Metadata, agent: &Agent) -> bool {
+        match self {
+            TargetSet::Bosses       => meta.bosses().any(|b| b == agent),
+            TargetSet::Species(s)   => agent.profession().species_id().map(|id| s.contains(&id)).unwrap_or(false),
+            TargetSet::Agents(a)    => a.contains(&agent.id()),
+            TargetSet::Predicate(f) => f(agent),
+            TargetSet::Any(s)       => s.iter().any(|t| t.contains(meta, agent)),
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Relating event times to wall-clock time.
+//!
+//! Event times are relative milliseconds as recorded by the PoV, the log start and end events
+//! record the matching server and local unix timestamps which are used as reference points.
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+/// A point in the log where the relative time is known in wall-clock time, recorded by the
+/// `LogStart` and `LogEnd` events.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Serialize)]
+pub struct Timestamp {
+    /// Relative time, milliseconds
+    time:      u64,
+    /// Server unix timestamp, seconds
+    server:    u32,
+    /// Local unix timestamp of the PoV, seconds
+    local:     u32,
+    #[serde(rename="arcdpsId")]
+    arcdps_id: u64,
+}
+
+impl Timestamp {
+    pub fn new(time: u64, server: u32, local: u32, arcdps_id: u64) -> Self {
+        Timestamp {
+            time,
+            server,
+            local,
+            arcdps_id,
+        }
+    }
+
+    /// Relative time of the timestamp, milliseconds
+    #[inline]
+    pub fn time(&self) -> u64 {
+        self.time
+    }
+
+    /// Server unix timestamp, seconds
+    #[inline]
+    pub fn server(&self) -> u32 {
+        self.server
+    }
+
+    /// Local unix timestamp of the PoV, seconds
+    #[inline]
+    pub fn local(&self) -> u32 {
+        self.local
+    }
+
+    #[inline]
+    pub fn arcdps_id(&self) -> u64 {
+        self.arcdps_id
+    }
+
+    /// Converts a relative event time to a UTC instant using the server clock.
+    pub fn to_utc(&self, time: u64) -> SystemTime {
+        offset(UNIX_EPOCH + Duration::from_secs(self.server as u64), self.time, time)
+    }
+
+    /// Converts a relative event time to an instant using the local clock of the PoV.
+    pub fn to_local(&self, time: u64) -> SystemTime {
+        offset(UNIX_EPOCH + Duration::from_secs(self.local as u64), self.time, time)
+    }
+
+    /// Converts a relative event time to a server unix timestamp in milliseconds.
+    pub fn to_unix_millis(&self, time: u64) -> u64 {
+        // Events before 1970 are not a concern
+        let d = self.to_utc(time).duration_since(UNIX_EPOCH).unwrap_or(Duration::from_secs(0));
+
+        d.as_secs() * 1000 + d.subsec_millis() as u64
+    }
+}
+
+fn offset(base: SystemTime, reference: u64, time: u64) -> SystemTime {
+    if time >= reference {
+        base + Duration::from_millis(time - reference)
+    }
+    else {
+        base - Duration::from_millis(reference - time)
+    }
+}
+
+/// Duration between two relative event times, zero if `to` is before `from`.
+#[inline]
+pub fn elapsed(from: u64, to: u64) -> Duration {
+    Duration::from_millis(to.saturating_sub(from))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn relative_to_unix() {
+        let t = Timestamp::new(10_000, 1_500_000_000, 1_500_000_002, 1);
+
+        assert_eq!(t.to_unix_millis(10_000), 1_500_000_000_000);
+        assert_eq!(t.to_unix_millis(12_345), 1_500_000_002_345);
+        assert_eq!(t.to_unix_millis(9_000),  1_499_999_999_000);
+        assert_eq!(t.to_local(10_000), UNIX_EPOCH + Duration::from_secs(1_500_000_002));
+        assert_eq!(elapsed(5, 3), Duration::from_millis(0));
+    }
+}
@@ -16,11 +16,9 @@ pub struct Series<E: Entry> {
 
 impl<E: Entry> Series<E> {
     pub fn new(meta: &Metadata) -> Self {
-        assert!(meta.log_end() > meta.log_start());
-
         Series {
             current: None,
-            series:  Vec::with_capacity(((meta.log_end() - meta.log_start()) / 1000) as usize),
+            series:  Vec::with_capacity((meta.encounter().duration_ms() / 1000) as usize),
         }
     }
 
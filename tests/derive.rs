@@ -0,0 +1,96 @@
+//! Tests for the `Sink` and `Merge` derives of `evtc_derive`, the generated code refers to the
+//! `evtc` crate by name and can only be compiled outside of it.
+extern crate evtc;
+
+use evtc::AgentId;
+
+use evtc::event::Event;
+use evtc::event::raw::CombatEventV1;
+use evtc::event::raw::HitResult;
+use evtc::statistics::IsCondi;
+use evtc::statistics::Merge;
+use evtc::statistics::Sink;
+use evtc::synthetic;
+
+/// Counts the events it receives.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+struct Count(u32);
+
+impl<T> Sink<T> for Count {
+    fn add_event(&mut self, _: T) {
+        self.0 += 1;
+    }
+}
+
+impl Merge for Count {
+    fn merge(&mut self, other: &Self) {
+        self.0 += other.0;
+    }
+}
+
+#[derive(Debug, Default, Clone, Sink, Merge)]
+#[sink(event = "::evtc::event::Damage")]
+struct Counts {
+    all:       Count,
+    #[sink(hit_type = "Crit | Glance")]
+    crit:      Count,
+    #[sink(exclude_hit_type = "Condi")]
+    power:     Count,
+    #[sink(filter = "IsCondi")]
+    condi:     Count,
+    #[sink(target_in = "targets")]
+    on_target: Count,
+    #[sink(skip)]
+    #[merge(skip)]
+    skipped:   Count,
+    #[sink(skip)]
+    #[merge(skip)]
+    targets:   Vec<AgentId>,
+}
+
+fn events() -> Vec<CombatEventV1> {
+    vec![
+        synthetic::Event::new(1000).src(1, 1).dst(2, 2).skill(100).damage(500, 0, HitResult::Normal).into(),
+        synthetic::Event::new(2000).src(1, 1).dst(2, 2).skill(100).damage(800, 0, HitResult::Crit).into(),
+        synthetic::Event::new(3000).src(1, 1).dst(3, 3).skill(100).damage(300, 0, HitResult::Glance).into(),
+        synthetic::Event::new(4000).src(1, 1).dst(3, 3).condi_damage(736, 50).into(),
+    ]
+}
+
+#[test]
+fn derive_sink() {
+    let events = events();
+    let mut c  = Counts { targets: vec![AgentId::new(2)], ..Default::default() };
+
+    for e in events.iter().filter_map(Event::into_damage) {
+        c.add_event(e);
+    }
+
+    assert_eq!(c.all, Count(4));
+    assert_eq!(c.crit, Count(2));
+    assert_eq!(c.power, Count(3));
+    assert_eq!(c.condi, Count(1));
+    assert_eq!(c.on_target, Count(2));
+    assert_eq!(c.skipped, Count(0));
+
+    // FromIterator starts from the default, without any targets
+    let c: Counts = events.iter().filter_map(Event::into_damage).collect();
+
+    assert_eq!(c.all, Count(4));
+    assert_eq!(c.on_target, Count(0));
+}
+
+#[test]
+fn derive_merge() {
+    let mut a = Counts { skipped: Count(1), targets: vec![AgentId::new(2)], ..Default::default() };
+    let b     = Counts { all: Count(2), crit: Count(1), skipped: Count(5), targets: vec![AgentId::new(3)], ..Default::default() };
+
+    a.merge(&b);
+    a.merge(&b);
+
+    assert_eq!(a.all, Count(4));
+    assert_eq!(a.crit, Count(2));
+    // Skipped fields are left as is
+    assert_eq!(a.skipped, Count(1));
+    assert_eq!(a.targets, vec![AgentId::new(2)]);
+}